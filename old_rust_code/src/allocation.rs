@@ -1,5 +1,6 @@
 use data::*;
 use candidate::*;
+use perturbation::PerturbedSolution;
 
 /// An event allocation.
 #[derive(Clone)]
@@ -7,7 +8,7 @@ pub struct Allocation {
     event_index: usize,
     timeslot_index: usize,
     room_index: usize,
-    violations: usize,
+    cost: Cost,
 }
 
 impl Allocation {
@@ -16,12 +17,12 @@ impl Allocation {
             event_index: event_index,
             timeslot_index: timeslot_index,
             room_index: room_index,
-            violations: 0,
+            cost: Cost::zero(),
         }
     }
 
-    pub fn set_violations(&mut self, violations: usize) {
-        self.violations = violations;
+    pub fn set_cost(&mut self, cost: Cost) {
+        self.cost = cost;
     }
 
     // accessors
@@ -31,9 +32,16 @@ impl Allocation {
         self.event_index
     }
 
+    /// The hard and soft penalties this allocation incurs.
+    #[inline(always)]
+    pub fn cost(&self) -> Cost {
+        self.cost
+    }
+
+    /// The total penalty of this allocation, hard and soft.
     #[inline(always)]
     pub fn violations(&self) -> usize {
-        self.violations
+        self.cost.total()
     }
 
     #[inline(always)]
@@ -51,3 +59,13 @@ pub trait AllocationStrategy {
     /// The 'main function' of the allocation strategy.
     fn allocate(&mut self, instance: &Instance) -> Box<[CandidateSolution]>;
 }
+
+pub trait ReallocationStrategy {
+    /// Put the displaced events (which must be unallocated) back into the candidate, returning
+    /// the altered solution.
+    fn reallocate(&mut self,
+                  candidate: &CandidateSolution,
+                  events: &[usize],
+                  instance: &Instance)
+                  -> PerturbedSolution;
+}
@@ -0,0 +1,270 @@
+use candidate::*;
+use allocation::*;
+use perturbation::*;
+use data::instance::*;
+
+/// the aspect of the currently allocated event to change.
+pub enum ToChange {
+    /// Change the room.
+    Room,
+    /// Change the timeslot.
+    Timeslot,
+    /// Move the event off of its current day altogether.
+    Day,
+}
+
+/// The event and "offending" item that needs changing.
+pub struct ReallocateTask {
+    /// The index of the event to reallocate in the Instance.
+    pub event_index: usize,
+    /// The specific aspect that needs to be changed.
+    pub to_change: ToChange,
+}
+
+/// A disruption to an already allocated timetable, such as a public holiday or a building
+/// closure.
+pub trait Alteration {
+    /// Ban the disrupted resource for every event in the instance, so that no event can be
+    /// reallocated into it.
+    fn restrict(&self, instance: &mut Instance);
+
+    /// Work out which of the allocated events are affected, and what needs to change about them.
+    fn tasks(&self, candidate: &CandidateSolution, instance: &Instance) -> Vec<ReallocateTask>;
+}
+
+/// No events can take place on the specified day.
+pub struct BanDay {
+    day: usize,
+}
+
+impl BanDay {
+    pub fn new(day: usize) -> Self {
+        BanDay { day }
+    }
+}
+
+impl Alteration for BanDay {
+    fn restrict(&self, instance: &mut Instance) {
+        let timeslots: Vec<_> = instance.timeslots()
+            .iter()
+            .filter(|timeslot| timeslot.day == self.day)
+            .copied()
+            .collect();
+        for event_index in 0..instance.num_events() {
+            let event = instance.event_mut(event_index).unwrap();
+            for timeslot in timeslots.iter() {
+                event.ban_timeslot(*timeslot);
+            }
+        }
+    }
+
+    fn tasks(&self, candidate: &CandidateSolution, instance: &Instance) -> Vec<ReallocateTask> {
+        let mut tasks = Vec::new();
+        for timeslot_index in 0..candidate.num_timeslots() {
+            if instance.timeslot(timeslot_index).unwrap().day != self.day {
+                continue;
+            }
+            for room_index in 0..candidate.num_rooms() {
+                if let Some(allocation) = candidate.get_allocation(timeslot_index, room_index) {
+                    tasks.push(ReallocateTask {
+                        event_index: allocation.event_index(),
+                        to_change: ToChange::Day,
+                    });
+                }
+            }
+        }
+        tasks
+    }
+}
+
+/// The specified room cannot be used at any time.
+pub struct BanRoom {
+    room_index: usize,
+}
+
+impl BanRoom {
+    pub fn new(room_index: usize) -> Self {
+        BanRoom { room_index }
+    }
+}
+
+impl Alteration for BanRoom {
+    fn restrict(&self, instance: &mut Instance) {
+        let num_rooms = instance.num_rooms();
+        for event_index in 0..instance.num_events() {
+            let event = instance.event_mut(event_index).unwrap();
+            event.ban_room(self.room_index, num_rooms);
+        }
+    }
+
+    fn tasks(&self, candidate: &CandidateSolution, _instance: &Instance) -> Vec<ReallocateTask> {
+        let mut tasks = Vec::new();
+        for timeslot_index in 0..candidate.num_timeslots() {
+            if let Some(allocation) = candidate.get_allocation(timeslot_index, self.room_index) {
+                tasks.push(ReallocateTask {
+                    event_index: allocation.event_index(),
+                    to_change: ToChange::Room,
+                });
+            }
+        }
+        tasks
+    }
+}
+
+/// No events can take place in the specified timeslot, in any room.
+pub struct BanTimeslot {
+    timeslot_index: usize,
+}
+
+impl BanTimeslot {
+    pub fn new(timeslot_index: usize) -> Self {
+        BanTimeslot { timeslot_index }
+    }
+}
+
+impl Alteration for BanTimeslot {
+    fn restrict(&self, instance: &mut Instance) {
+        let timeslot = *instance.timeslot(self.timeslot_index)
+            .expect("Invalid timeslot index provided to BanTimeslot.");
+        for event_index in 0..instance.num_events() {
+            let event = instance.event_mut(event_index).unwrap();
+            event.ban_timeslot(timeslot);
+        }
+    }
+
+    fn tasks(&self, candidate: &CandidateSolution, _instance: &Instance) -> Vec<ReallocateTask> {
+        let mut tasks = Vec::new();
+        for room_index in 0..candidate.num_rooms() {
+            if let Some(allocation) = candidate.get_allocation(self.timeslot_index, room_index) {
+                tasks.push(ReallocateTask {
+                    event_index: allocation.event_index(),
+                    to_change: ToChange::Timeslot,
+                });
+            }
+        }
+        tasks
+    }
+}
+
+/// Apply the alteration to the candidate, reallocating every affected event with the strategy.
+pub fn alter(alteration: &dyn Alteration,
+             candidate: CandidateSolution,
+             instance: &mut Instance,
+             strategy: &mut dyn ReallocationStrategy)
+             -> PerturbedSolution {
+    let tasks = alteration.tasks(&candidate, instance);
+    alteration.restrict(instance);
+    reallocate_events(tasks, candidate, instance, strategy)
+}
+
+/// Unallocate the events that are specified in the tasks, adjusting the availability
+/// in the instance.
+fn unallocate_events(tasks: Vec<ReallocateTask>,
+                     candidate: &mut CandidateSolution,
+                     instance: &mut Instance)
+                     -> Vec<usize> {
+    let num_rooms = instance.num_rooms();
+    // Delete those events from the candidate:
+    for task in tasks.iter() {
+        // If an earlier task has already removed the event, this finds nothing.
+        while let Some((timeslot_index, room_index)) =
+                  candidate.unallocate_event(task.event_index, instance) {
+            // Adjust the event in the instance to compensate.
+            let timeslot = *instance.timeslot(timeslot_index).unwrap();
+            let days_timeslots: Vec<_> = instance.timeslots()
+                .iter()
+                .filter(|other| other.day == timeslot.day)
+                .copied()
+                .collect();
+            let event = instance.event_mut(task.event_index).unwrap();
+            match task.to_change {
+                ToChange::Room => {
+                    // Remove the room from the list of valid rooms.
+                    event.ban_room(room_index, num_rooms);
+                }
+                ToChange::Timeslot => {
+                    // Add the timeslot to the list of banned timeslots.
+                    event.ban_timeslot(timeslot);
+                }
+                ToChange::Day => {
+                    // Ban every timeslot on the same day.
+                    for timeslot in days_timeslots {
+                        event.ban_timeslot(timeslot);
+                    }
+                }
+            }
+        }
+    }
+    // Return a vector of the event indecies we need to reallocate.
+    tasks.iter().map(|to_change| to_change.event_index).collect()
+}
+
+/// Perform the specified reallocate tasks on the CandidateSolution of the Instance, using the
+/// strategy to find the displaced events a new home.
+pub fn reallocate_events(tasks: Vec<ReallocateTask>,
+                         mut candidate: CandidateSolution,
+                         instance: &mut Instance,
+                         strategy: &mut dyn ReallocationStrategy)
+                         -> PerturbedSolution {
+    // In order to _RE_allocate events, first we must:
+    let events = unallocate_events(tasks, &mut candidate, instance);
+    // And then find them a new home:
+    strategy.reallocate(&candidate, &events, instance)
+}
+
+#[cfg(test)]
+mod tests {
+    use data;
+    use super::*;
+
+    /// Allocates every event of the test instance, filling up the timeslots in order.
+    fn allocated_candidate(instance: &Instance) -> CandidateSolution {
+        let mut candidate = CandidateSolution::new(instance);
+        for event_index in 0..instance.num_events() {
+            let timeslot_index = event_index % instance.num_timeslots();
+            let room_index = event_index / instance.num_timeslots();
+            candidate.allocate_event(timeslot_index, room_index, Some(event_index), instance);
+        }
+        candidate
+    }
+
+    /// Alters the test instance, and checks that no event is left in a disrupted cell, and that
+    /// every event is either allocated or reported as unplaced.
+    fn assert_alteration_kept<F>(name: &str, alteration: &dyn Alteration, is_disrupted: F)
+        where F: Fn((usize, usize), &Instance) -> bool
+    {
+        let mut instance = data::test_instance(name);
+        let candidate = allocated_candidate(&instance);
+        let mut strategy = MinimalPerturbationStrategy::new();
+        let result = alter(alteration, candidate, &mut instance, &mut strategy);
+
+        let candidate = result.candidate();
+        for allocation in candidate.allocations() {
+            let allocation_index = (allocation.timeslot_index(), allocation.room_index());
+            assert!(!is_disrupted(allocation_index, &instance));
+        }
+        for event_index in 0..instance.num_events() {
+            assert!(candidate.allocation_index_of(event_index).is_some() ||
+                    result.unplaced_events().contains(&event_index));
+        }
+    }
+
+    #[test]
+    fn ban_day_moves_every_event_off_the_day() {
+        assert_alteration_kept("ban_day", &BanDay::new(0), |(timeslot_index, _), instance| {
+            instance.timeslot(timeslot_index).unwrap().day == 0
+        });
+    }
+
+    #[test]
+    fn ban_room_moves_every_event_out_of_the_room() {
+        assert_alteration_kept("ban_room", &BanRoom::new(0), |(_, room_index), _| room_index == 0);
+    }
+
+    #[test]
+    fn ban_timeslot_moves_every_event_out_of_the_timeslot() {
+        assert_alteration_kept("ban_timeslot", &BanTimeslot::new(1), |(timeslot_index, _), _| {
+            timeslot_index == 1
+        });
+    }
+}
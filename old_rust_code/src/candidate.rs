@@ -1,15 +1,231 @@
+use std::collections::HashMap;
+use std::ops::AddAssign;
+use std::rc::Rc;
+
 use data::*;
 use allocation::Allocation;
 use boxed_slice2d::BoxedSlice2D;
 
+/// The penalty of a solution (or part of one), keeping hard and soft constraint violations
+/// apart. Costs are ordered by their hard penalty first, so that a feasible timetable is always
+/// better than an infeasible one.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Cost {
+    pub hard: usize,
+    pub soft: usize,
+}
+
+impl Cost {
+    pub fn zero() -> Self {
+        Cost { hard: 0, soft: 0 }
+    }
+
+    /// Add the penalty of violating a constraint of the specified kind.
+    pub fn add(&mut self, kind: ConstraintKind, penalty: usize) {
+        match kind {
+            ConstraintKind::Hard => self.hard += penalty,
+            ConstraintKind::Soft => self.soft += penalty,
+        }
+    }
+
+    /// Take away a penalty that was added with add().
+    fn remove(&mut self, kind: ConstraintKind, penalty: usize) {
+        match kind {
+            ConstraintKind::Hard => self.hard -= penalty,
+            ConstraintKind::Soft => self.soft -= penalty,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.hard + self.soft
+    }
+}
+
+/// The change in cost a move would make. Negative penalties are an improvement.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CostDelta {
+    pub hard: isize,
+    pub soft: isize,
+}
+
+impl CostDelta {
+    pub fn zero() -> Self {
+        CostDelta { hard: 0, soft: 0 }
+    }
+
+    /// The change from one cost to another.
+    pub fn between(before: Cost, after: Cost) -> Self {
+        CostDelta {
+            hard: after.hard as isize - before.hard as isize,
+            soft: after.soft as isize - before.soft as isize,
+        }
+    }
+
+    /// Add a change in the penalty of a constraint of the specified kind.
+    fn add(&mut self, kind: ConstraintKind, penalty: isize) {
+        match kind {
+            ConstraintKind::Hard => self.hard += penalty,
+            ConstraintKind::Soft => self.soft += penalty,
+        }
+    }
+
+    pub fn total(&self) -> isize {
+        self.hard + self.soft
+    }
+}
+
+impl AddAssign for CostDelta {
+    fn add_assign(&mut self, other: CostDelta) {
+        self.hard += other.hard;
+        self.soft += other.soft;
+    }
+}
+
+/// A cell, as a (timeslot, room) index, and the event that it holds or would hold.
+type Cell = ((usize, usize), Option<usize>);
+
+/// The teacher and curricula of each event, numbered so that they can index the counters of a
+/// CandidateSolution, the events of each course and curriculum, and the groups of the instance's
+/// group constraints. These never change, so they are shared between clones.
+struct EventKeys {
+    /// The teacher number of each event.
+    teachers: Vec<usize>,
+    /// The curriculum numbers of each event.
+    curricula: Vec<Vec<usize>>,
+    num_teachers: usize,
+    num_curricula: usize,
+    /// The indices of the events of each course, by course id.
+    course_events: HashMap<String, Vec<usize>>,
+    /// The indices of the events in each curriculum, by curriculum id.
+    curriculum_events: HashMap<String, Vec<usize>>,
+    /// The number of each curriculum, by curriculum id.
+    curriculum_numbers: HashMap<String, usize>,
+    /// Every group of every group constraint, paired with the index of the constraint. The
+    /// position of a group in this list is its group number.
+    groups: Vec<(usize, Group)>,
+    /// The number of each group.
+    group_numbers: HashMap<(usize, Group), usize>,
+    /// The numbers of the groups each event belongs to, wherever it is allocated.
+    event_groups: Vec<Vec<usize>>,
+    /// The indices of the group constraints whose groups depend on where an event is allocated,
+    /// rather than on the event.
+    located_constraints: Vec<usize>,
+}
+
+impl EventKeys {
+    fn new(instance: &Instance) -> Self {
+        let mut teacher_ids: Vec<&str> = Vec::new();
+        let mut curriculum_ids: Vec<&str> = Vec::new();
+        let mut teachers = Vec::with_capacity(instance.num_events());
+        let mut curricula = Vec::with_capacity(instance.num_events());
+        let mut course_events = HashMap::new();
+        let mut curriculum_events = HashMap::new();
+        for (event_index, event) in instance.events().iter().enumerate() {
+            teachers.push(number(&mut teacher_ids, event.teacher()));
+            curricula.push(event.curriculum_ids()
+                .iter()
+                .map(|id| number(&mut curriculum_ids, id))
+                .collect());
+            course_events.entry(event.course_id().to_owned())
+                .or_insert_with(Vec::new)
+                .push(event_index);
+            for curriculum_id in event.curriculum_ids() {
+                curriculum_events.entry(curriculum_id.clone())
+                    .or_insert_with(Vec::new)
+                    .push(event_index);
+            }
+        }
+
+        let mut groups = Vec::new();
+        let mut group_numbers = HashMap::new();
+        let mut located_constraints = Vec::new();
+        for (constraint_index, constraint) in instance.group_constraints().iter().enumerate() {
+            if !is_event_scope(constraint.scope()) {
+                located_constraints.push(constraint_index);
+            }
+            for group in constraint.scope().groups(instance) {
+                group_numbers.insert((constraint_index, group.clone()), groups.len());
+                groups.push((constraint_index, group));
+            }
+        }
+        let event_groups = instance.events()
+            .iter()
+            .map(|event| {
+                let mut numbers = Vec::new();
+                for (constraint_index, constraint) in instance.group_constraints()
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, constraint)| is_event_scope(constraint.scope())) {
+                    // Where the event is allocated makes no difference to these groups.
+                    for group in constraint.scope().groups_of(event, (0, 0), instance) {
+                        numbers.push(group_numbers[&(constraint_index, group)]);
+                    }
+                }
+                numbers
+            })
+            .collect();
+
+        let curriculum_numbers = curriculum_ids.iter()
+            .enumerate()
+            .map(|(curriculum, &id)| (id.to_owned(), curriculum))
+            .collect();
+
+        EventKeys {
+            teachers,
+            curricula,
+            num_teachers: teacher_ids.len(),
+            num_curricula: curriculum_ids.len(),
+            course_events,
+            curriculum_events,
+            curriculum_numbers,
+            groups,
+            group_numbers,
+            event_groups,
+            located_constraints,
+        }
+    }
+}
+
+/// The position of the id in the list, adding it to the end if it isn't there yet.
+fn number<'a>(ids: &mut Vec<&'a str>, id: &'a str) -> usize {
+    match ids.iter().position(|&other| other == id) {
+        Some(position) => position,
+        None => {
+            ids.push(id);
+            ids.len() - 1
+        }
+    }
+}
+
+/// Returns true if the groups of the scope only depend on which events are in them.
+fn is_event_scope(scope: Scope) -> bool {
+    match scope {
+        Scope::Course | Scope::Curriculum | Scope::Teacher => true,
+        Scope::Cell | Scope::Day | Scope::Solution => false,
+    }
+}
+
 /// A possible solution to a Timetable Problem instance.
 /// Really a wrapper to the set of allocations an algorithm has
 /// generated.
 #[derive(Clone)]
 pub struct CandidateSolution {
     allocation_table: BoxedSlice2D<Option<Allocation>>,
-    violations: usize, // TODO(zac): Might want to add hard and soft constraints later.
+    /// The cost of the allocations. Does not include the unallocated events.
+    cost: Cost,
     num_unallocated_events: usize,
+    /// The (timeslot, room) index of each cell each event is allocated to, in timeslot then room
+    /// order. Normally there is at most one, but crossover can leave an event in more than one
+    /// cell.
+    event_cells: Vec<Vec<(usize, usize)>>,
+    /// The number of lectures each teacher has in each timeslot, indexed by (timeslot, teacher).
+    teacher_lectures: BoxedSlice2D<usize>,
+    /// The number of lectures each curriculum has in each timeslot, indexed by
+    /// (timeslot, curriculum).
+    curriculum_lectures: BoxedSlice2D<usize>,
+    /// The violations of each group, by group number.
+    group_violations: Vec<usize>,
+    keys: Rc<EventKeys>,
 }
 
 impl CandidateSolution {
@@ -19,11 +235,22 @@ impl CandidateSolution {
         let width = instance.timeslots().len();
         let height = instance.rooms().len();
         let table = BoxedSlice2D::new(width, height);
-        CandidateSolution {
+        let keys = EventKeys::new(instance);
+        let mut candidate = CandidateSolution {
             allocation_table: table,
-            violations: 0,
-            num_unallocated_events: usize::max_value(),
-        }
+            cost: Cost::zero(),
+            num_unallocated_events: instance.num_events(),
+            event_cells: vec![Vec::new(); instance.num_events()],
+            teacher_lectures: BoxedSlice2D::new(width, keys.num_teachers),
+            curriculum_lectures: BoxedSlice2D::new(width, keys.num_curricula),
+            group_violations: vec![0; keys.groups.len()],
+            keys: Rc::new(keys),
+        };
+
+        // Some constraints on groups of events are broken before anything is allocated.
+        let groups: Vec<usize> = (0..candidate.keys.groups.len()).collect();
+        candidate.refresh_groups(&groups, instance);
+        candidate
     }
 
     /// Allocate a specified event (index) to the specified room (index) and timeslot (index),
@@ -33,50 +260,340 @@ impl CandidateSolution {
                           room_index: usize,
                           event_index: Option<usize>,
                           instance: &Instance) {
-        let mut allocation = if let Some(event_index) = event_index {
-            Some(Allocation::new(event_index, timeslot_index, room_index))
-        } else {
-            None
+        self.change(&[((timeslot_index, room_index), event_index)], instance);
+    }
+
+    /// Swap the contents of two cells, each given as a (timeslot, room) index. Moving an event
+    /// to an empty cell is a swap with that cell.
+    pub fn swap(&mut self, a: (usize, usize), b: (usize, usize), instance: &Instance) {
+        if a == b {
+            return;
+        }
+        let changes = [(a, self.event_at(b)), (b, self.event_at(a))];
+        self.change(&changes, instance);
+    }
+
+    /// Put each event into its cell, then update the cost of the changed timeslots and of the
+    /// groups of the events moved in or out.
+    fn change(&mut self, changes: &[Cell], instance: &Instance) {
+        // The groups of events this change affects.
+        let groups = self.affected_groups(&self.moved_cells(changes), instance);
+
+        let mut timeslots = Vec::with_capacity(changes.len());
+        for &(allocation_index, event_index) in changes.iter() {
+            // slot borrow scope:
+            let old_event_index = {
+                let slot = &mut self.allocation_table[allocation_index];
+                let old_event_index = slot.as_ref().map(|allocation| allocation.event_index());
+                if let &mut Some(ref slot) = slot {
+                    // Remove the cost of this slot from the allocation.
+                    self.cost.hard -= slot.cost().hard;
+                    self.cost.soft -= slot.cost().soft;
+                }
+                *slot = event_index.map(|event_index| {
+                    Allocation::new(event_index, allocation_index.0, allocation_index.1)
+                });
+                old_event_index
+            };
+            if let Some(old_event_index) = old_event_index {
+                self.uncount(allocation_index, old_event_index);
+            }
+            if let Some(event_index) = event_index {
+                self.count(allocation_index, event_index);
+            }
+            if !timeslots.contains(&allocation_index.0) {
+                timeslots.push(allocation_index.0);
+            }
+        }
+
+        // The change can add or remove a clash with any other allocation in the timeslot, so
+        // check them all again, now that the events are in place.
+        for timeslot_index in timeslots {
+            self.refresh_timeslot(timeslot_index, instance);
+        }
+
+        // Update candidate cost with the change in the affected groups.
+        self.refresh_groups(&groups, instance);
+    }
+
+    /// The change in cost swap() would make, without changing the candidate.
+    pub fn swap_delta(&self,
+                      a: (usize, usize),
+                      b: (usize, usize),
+                      instance: &Instance)
+                      -> CostDelta {
+        if a == b {
+            return CostDelta::zero();
+        }
+        self.change_delta(&[(a, self.event_at(b)), (b, self.event_at(a))], instance)
+    }
+
+    /// The change in cost allocate_event() would make, without changing the candidate.
+    pub fn allocate_delta(&self,
+                          timeslot_index: usize,
+                          room_index: usize,
+                          event_index: Option<usize>,
+                          instance: &Instance)
+                          -> CostDelta {
+        self.change_delta(&[((timeslot_index, room_index), event_index)], instance)
+    }
+
+    /// The change in cost that allocating each event to its (timeslot, room) index would make,
+    /// as calling allocate_event() for each of them in turn would. No index should appear more
+    /// than once.
+    pub fn allocations_delta(&self,
+                             allocations: &[((usize, usize), Option<usize>)],
+                             instance: &Instance)
+                             -> CostDelta {
+        self.change_delta(allocations, instance)
+    }
+
+    /// The change in cost that putting each event into its cell would make. Only the changed
+    /// cells and the groups of the events moved in or out are checked again, with the clashes
+    /// the other allocations in their timeslots gain or lose worked out from the counts.
+    fn change_delta(&self, changes: &[Cell], instance: &Instance) -> CostDelta {
+        let before = self.timetable();
+        let timetable = Timetable {
+            candidate: self,
+            changes,
         };
+        let mut delta = CostDelta::zero();
 
-        // Check for violations:
-        if let Some(allocation) = allocation.as_mut() {
-            // TODO(zac): We are not checking all constraints yet.
-            let event_index = event_index.unwrap();
-            let event = instance.event(event_index)
-                .expect("Invalid event index provided to CandidateSolution::allocate_event()");
+        for &(allocation_index, _) in changes.iter() {
+            let old_cost = self.get_allocation_with_index(allocation_index)
+                .map_or(Cost::zero(), |allocation| allocation.cost());
+            let cost = cell_cost(&timetable, allocation_index, instance);
+            delta += CostDelta::between(old_cost, cost);
+        }
+        // The changed cells, in timeslot order so that those in each timeslot are together.
+        let mut changed_cells: Vec<(usize, usize)> =
+            changes.iter().map(|&(allocation_index, _)| allocation_index).collect();
+        changed_cells.sort();
+        for cells in changed_cells.chunk_by(|a, b| a.0 == b.0) {
+            for constraint in instance.constraints() {
+                let change = constraint.check_timeslot_change(&before, &timetable, cells, instance);
+                delta.add(constraint.kind(), change * constraint.weight() as isize);
+            }
+        }
+
+        let cells = self.moved_cells(changes);
+        for group in self.affected_groups(&cells, instance) {
+            let (constraint_index, ref group_events) = self.keys.groups[group];
+            let constraint = &instance.group_constraints()[constraint_index];
+            let change = constraint.check_group_change(&before,
+                                                       &timetable,
+                                                       group_events,
+                                                       &changed_cells,
+                                                       instance)
+                .unwrap_or_else(|| {
+                    let violations = constraint.check_group(&timetable, group_events, instance);
+                    violations as isize - self.group_violations[group] as isize
+                });
+            delta.add(constraint.kind(), change * constraint.weight() as isize);
+        }
 
-            let mut violations = 0;
-            for constraint in event.constraints().iter() {
-                violations +=
-                    constraint.check_for_violations(self, (timeslot_index, room_index), instance);
+        // Each event that becomes allocated or unallocated.
+        let (moved_out, moved_in) = cells.split_at(changes.len());
+        for (position, &(_, event_index)) in cells.iter().enumerate() {
+            let event_index = match event_index {
+                Some(event_index) => event_index,
+                None => continue,
+            };
+            if cells[..position].iter().any(|cell| cell.1 == Some(event_index)) {
+                continue;
             }
+            let removed = moved_out.iter().filter(|cell| cell.1 == Some(event_index)).count();
+            let added = moved_in.iter().filter(|cell| cell.1 == Some(event_index)).count();
+            let before = self.event_cells[event_index].len();
+            let after = before + added - removed;
+            if before == 0 && after > 0 {
+                delta.hard -= 1;
+            } else if before > 0 && after == 0 {
+                delta.hard += 1;
+            }
+        }
+        delta
+    }
+
+    /// The events the changes would move out of each cell, followed by the events they would
+    /// move in.
+    fn moved_cells(&self, changes: &[Cell]) -> Vec<Cell> {
+        let mut cells = Vec::with_capacity(changes.len() * 2);
+        for &(allocation_index, _) in changes.iter() {
+            cells.push((allocation_index, self.event_at(allocation_index)));
+        }
+        cells.extend_from_slice(changes);
+        cells
+    }
+
+    /// Count a lecture of the event in the cell at the allocation index.
+    fn count(&mut self, allocation_index: (usize, usize), event_index: usize) {
+        let timeslot_index = allocation_index.0;
+        let cells = &mut self.event_cells[event_index];
+        if cells.is_empty() {
+            self.num_unallocated_events -= 1;
+        }
+        let position = cells.binary_search(&allocation_index).unwrap_or_else(|position| position);
+        cells.insert(position, allocation_index);
+        self.teacher_lectures[(timeslot_index, self.keys.teachers[event_index])] += 1;
+        for &curriculum in self.keys.curricula[event_index].iter() {
+            self.curriculum_lectures[(timeslot_index, curriculum)] += 1;
+        }
+    }
+
+    /// Stop counting a lecture of the event in the cell at the allocation index, which must no
+    /// longer hold it.
+    fn uncount(&mut self, allocation_index: (usize, usize), event_index: usize) {
+        let timeslot_index = allocation_index.0;
+        let cells = &mut self.event_cells[event_index];
+        if let Some(position) = cells.iter().position(|&cell| cell == allocation_index) {
+            cells.remove(position);
+        }
+        if cells.is_empty() {
+            self.num_unallocated_events += 1;
+        }
+        self.teacher_lectures[(timeslot_index, self.keys.teachers[event_index])] -= 1;
+        for &curriculum in self.keys.curricula[event_index].iter() {
+            self.curriculum_lectures[(timeslot_index, curriculum)] -= 1;
+        }
+    }
 
-            for constraint in instance.constraints().iter() {
-                violations +=
-                    constraint.check_for_violations(self, (timeslot_index, room_index), instance);
+    /// The number of lectures allocated to the timeslot that are taught by the event's teacher,
+    /// including the event itself if it is there.
+    #[inline(always)]
+    pub fn teacher_lectures(&self, timeslot_index: usize, event_index: usize) -> usize {
+        self.teacher_lectures[(timeslot_index, self.keys.teachers[event_index])]
+    }
+
+    /// The allocations of this candidate, for checking constraints against.
+    pub fn timetable(&self) -> Timetable<'_> {
+        Timetable {
+            candidate: self,
+            changes: &[],
+        }
+    }
+
+    /// Re-evaluate the cost of every allocation in the timeslot.
+    fn refresh_timeslot(&mut self, timeslot_index: usize, instance: &Instance) {
+        for room_index in 0..self.num_rooms() {
+            let old_cost = match self.get_allocation(timeslot_index, room_index) {
+                Some(allocation) => allocation.cost(),
+                None => continue,
+            };
+            let cost = cell_cost(&self.timetable(), (timeslot_index, room_index), instance);
+            if let Some(allocation) = self.allocation_table[(timeslot_index, room_index)].as_mut() {
+                allocation.set_cost(cost);
             }
+            self.cost.hard = self.cost.hard + cost.hard - old_cost.hard;
+            self.cost.soft = self.cost.soft + cost.soft - old_cost.soft;
+        }
+    }
 
-            // Update the violations field of allocation.
-            allocation.set_violations(violations);
-            // Update candidate violations.
-            self.violations += violations;
+    /// Re-evaluate the violations of the groups, given by group number.
+    fn refresh_groups(&mut self, groups: &[usize], instance: &Instance) {
+        let keys = Rc::clone(&self.keys);
+        for &group in groups.iter() {
+            let (constraint_index, ref group_events) = keys.groups[group];
+            let constraint = &instance.group_constraints()[constraint_index];
+            let violations = constraint.check_group(&self.timetable(), group_events, instance);
+            self.cost.remove(constraint.kind(),
+                             self.group_violations[group] * constraint.weight());
+            self.cost.add(constraint.kind(), violations * constraint.weight());
+            self.group_violations[group] = violations;
         }
-        // slot borrow scope:
-        {
-            let slot = &mut self.allocation_table[(timeslot_index, room_index)];
-            if let &mut Some(ref slot) = slot {
-                // Remove the cost of this slot from the allocation.
-                self.violations -= slot.violations();
+    }
+
+    /// The numbers of the groups that the events belong to, when allocated to their cells.
+    fn affected_groups(&self, cells: &[Cell], instance: &Instance) -> Vec<usize> {
+        let mut groups = Vec::new();
+        for &(allocation_index, event_index) in cells.iter() {
+            let event_index = match event_index {
+                Some(event_index) => event_index,
+                None => continue,
+            };
+            for &group in self.keys.event_groups[event_index].iter() {
+                if !groups.contains(&group) {
+                    groups.push(group);
+                }
+            }
+            if self.keys.located_constraints.is_empty() {
+                continue;
+            }
+            let event = instance.event(event_index).expect("Invalid event index provided.");
+            for &constraint_index in self.keys.located_constraints.iter() {
+                let scope = instance.group_constraints()[constraint_index].scope();
+                for group in scope.groups_of(event, allocation_index, instance) {
+                    let group = self.keys.group_numbers[&(constraint_index, group)];
+                    if !groups.contains(&group) {
+                        groups.push(group);
+                    }
+                }
             }
-            *slot = allocation;
         }
-        self.check_all_events_allocated(instance);
+        groups
+    }
+
+    /// Rebuild the counts and re-evaluate every allocation and group, replacing the costs that
+    /// have been kept up to date as events were allocated. Returns the new cost, which should
+    /// always equal cost() beforehand.
+    pub fn recompute_from_scratch(&mut self, instance: &Instance) -> Cost {
+        for cells in self.event_cells.iter_mut() {
+            cells.clear();
+        }
+        self.num_unallocated_events = instance.num_events();
+        self.teacher_lectures = BoxedSlice2D::new(self.num_timeslots(), self.keys.num_teachers);
+        self.curriculum_lectures = BoxedSlice2D::new(self.num_timeslots(),
+                                                     self.keys.num_curricula);
+        for timeslot_index in 0..self.num_timeslots() {
+            for room_index in 0..self.num_rooms() {
+                if let Some(event_index) = self.event_at((timeslot_index, room_index)) {
+                    self.count((timeslot_index, room_index), event_index);
+                }
+            }
+        }
+
+        self.cost = Cost::zero();
+        for timeslot_index in 0..self.num_timeslots() {
+            for room_index in 0..self.num_rooms() {
+                if let Some(allocation) = self.allocation_table[(timeslot_index, room_index)]
+                    .as_mut() {
+                    allocation.set_cost(Cost::zero());
+                }
+            }
+            self.refresh_timeslot(timeslot_index, instance);
+        }
+        for violations in self.group_violations.iter_mut() {
+            *violations = 0;
+        }
+        let groups: Vec<usize> = (0..self.keys.groups.len()).collect();
+        self.refresh_groups(&groups, instance);
+        self.cost()
+    }
+
+    /// The hard and soft penalties of this candidate. Each unallocated event counts as a hard
+    /// violation.
+    #[inline(always)]
+    pub fn cost(&self) -> Cost {
+        Cost {
+            hard: self.cost.hard + self.num_unallocated_events(),
+            soft: self.cost.soft,
+        }
     }
 
+    #[inline(always)]
+    pub fn hard_violations(&self) -> usize {
+        self.cost().hard
+    }
+
+    #[inline(always)]
+    pub fn soft_penalty(&self) -> usize {
+        self.cost().soft
+    }
+
+    /// The total penalty of this candidate, hard and soft.
     #[inline(always)]
     pub fn violations(&self) -> usize {
-        self.violations + self.num_unallocated_events()
+        self.cost().total()
     }
 
     #[inline(always)]
@@ -94,18 +611,78 @@ impl CandidateSolution {
         self.num_unallocated_events
     }
 
-    /// Checks and updates the number of unallocated events.
+    /// Recounts the number of unallocated events from the allocation table. allocate_event()
+    /// keeps the count up to date, so this is only needed as a check.
     pub fn check_all_events_allocated(&mut self, instance: &Instance) {
-        let mut events_allocated = 0;
+        let mut allocated = vec![false; instance.num_events()];
         for t_index in 0..instance.num_timeslots() {
             for r_index in 0..instance.num_rooms() {
-                let allocation = self.get_allocation(t_index, r_index);
-                if allocation.is_some() {
-                    events_allocated += 1;
+                if let Some(allocation) = self.get_allocation(t_index, r_index) {
+                    allocated[allocation.event_index()] = true;
+                }
+            }
+        }
+        self.num_unallocated_events = allocated.iter().filter(|&&allocated| !allocated).count();
+    }
+
+    /// Every allocation that has been made in this candidate.
+    pub fn allocations(&self) -> Vec<&Allocation> {
+        let mut allocations = Vec::new();
+        for timeslot_index in 0..self.num_timeslots() {
+            for room_index in 0..self.num_rooms() {
+                if let Some(allocation) = self.get_allocation(timeslot_index, room_index) {
+                    allocations.push(allocation);
                 }
             }
         }
-        self.num_unallocated_events = instance.num_events() - events_allocated;
+        allocations
+    }
+
+    /// The (timeslot, room) index the event is allocated to, or None if it is unallocated.
+    #[inline(always)]
+    pub fn allocation_index_of(&self, event_index: usize) -> Option<(usize, usize)> {
+        self.event_cells[event_index].first().cloned()
+    }
+
+    /// The indices of the events that are not allocated anywhere, in order.
+    pub fn unallocated_events(&self) -> Vec<usize> {
+        self.event_cells
+            .iter()
+            .enumerate()
+            .filter(|&(_, cells)| cells.is_empty())
+            .map(|(event_index, _)| event_index)
+            .collect()
+    }
+
+    /// Move the event to the cell at the allocation index. Whatever was there takes the event's
+    /// old place, or becomes unallocated if the event was not allocated.
+    pub fn move_event(&mut self,
+                      event_index: usize,
+                      allocation_index: (usize, usize),
+                      instance: &Instance) {
+        match self.allocation_index_of(event_index) {
+            Some(old_index) => self.swap(old_index, allocation_index, instance),
+            None => {
+                self.allocate_event(allocation_index.0,
+                                    allocation_index.1,
+                                    Some(event_index),
+                                    instance)
+            }
+        }
+    }
+
+    /// Remove the event from the cell it is allocated to, returning the (timeslot, room) index
+    /// of that cell. Crossover can leave an event in more than one cell, in which case it is
+    /// still allocated to the others.
+    pub fn unallocate_event(&mut self,
+                            event_index: usize,
+                            instance: &Instance)
+                            -> Option<(usize, usize)> {
+        let allocation_index = self.allocation_index_of(event_index);
+        if let Some((timeslot_index, room_index)) = allocation_index {
+            self.allocate_event(timeslot_index, room_index, None, instance);
+        }
+        allocation_index
     }
 
     pub fn get_allocation(&self, timeslot: usize, room: usize) -> Option<&Allocation> {
@@ -115,4 +692,275 @@ impl CandidateSolution {
     pub fn get_allocation_with_index(&self, index: (usize, usize)) -> Option<&Allocation> {
         self.allocation_table[index].as_ref()
     }
+
+    /// The index of the event allocated to the cell at the (timeslot, room) index.
+    #[inline(always)]
+    fn event_at(&self, index: (usize, usize)) -> Option<usize> {
+        self.allocation_table[index].as_ref().map(|allocation| allocation.event_index())
+    }
+}
+
+/// The cost of the allocation at the allocation index, according to the constraints on its
+/// event and the instance.
+fn cell_cost(timetable: &Timetable, allocation_index: (usize, usize), instance: &Instance) -> Cost {
+    let mut cost = Cost::zero();
+    if let Some(event_index) = timetable.event_at(allocation_index) {
+        // TODO(zac): We are not checking all constraints yet.
+        let event = instance.event(event_index)
+            .expect("Invalid event index found in an allocation.");
+        for constraint in event.constraints().iter().chain(instance.constraints().iter()) {
+            let violations = constraint.check_for_violations(timetable, allocation_index, instance);
+            cost.add(constraint.kind(), violations * constraint.weight());
+        }
+    }
+    cost
+}
+
+/// The allocations of a candidate as they are, or as they would be after some of its cells were
+/// changed. Constraints are checked against a Timetable, so that the cost of a change can be
+/// worked out without making it.
+#[derive(Clone, Copy)]
+pub struct Timetable<'a> {
+    candidate: &'a CandidateSolution,
+    /// The changed cells, with the event each would hold. No cell appears more than once.
+    changes: &'a [Cell],
+}
+
+impl<'a> Timetable<'a> {
+    #[inline(always)]
+    pub fn num_timeslots(&self) -> usize {
+        self.candidate.num_timeslots()
+    }
+
+    #[inline(always)]
+    pub fn num_rooms(&self) -> usize {
+        self.candidate.num_rooms()
+    }
+
+    /// The index of the event allocated to the cell at the (timeslot, room) index.
+    pub fn event_at(&self, index: (usize, usize)) -> Option<usize> {
+        for &(cell, event_index) in self.changes.iter() {
+            if cell == index {
+                return event_index;
+            }
+        }
+        self.candidate.event_at(index)
+    }
+
+    /// The (timeslot, room) index the event is allocated to, or None if it is unallocated. If
+    /// the event is in more than one cell, this is the first of them in timeslot then room
+    /// order.
+    pub fn allocation_index_of(&self, event_index: usize) -> Option<(usize, usize)> {
+        let mut first = self.candidate.event_cells[event_index]
+            .iter()
+            .cloned()
+            .find(|&cell| !self.is_changed(cell));
+        for &(cell, new_event_index) in self.changes.iter() {
+            if new_event_index == Some(event_index) && first.is_none_or(|first| cell < first) {
+                first = Some(cell);
+            }
+        }
+        first
+    }
+
+    /// The number of lectures allocated to the timeslot that are taught by the event's teacher,
+    /// including the event itself if it is there.
+    pub fn teacher_lectures(&self, timeslot_index: usize, event_index: usize) -> usize {
+        let teachers = &self.candidate.keys.teachers;
+        let teacher = teachers[event_index];
+        let mut lectures = self.candidate.teacher_lectures[(timeslot_index, teacher)];
+        for &(cell, new_event_index) in self.changes.iter() {
+            if cell.0 != timeslot_index {
+                continue;
+            }
+            if self.candidate.event_at(cell).is_some_and(|old| teachers[old] == teacher) {
+                lectures -= 1;
+            }
+            if new_event_index.is_some_and(|new| teachers[new] == teacher) {
+                lectures += 1;
+            }
+        }
+        lectures
+    }
+
+    /// The number of other lectures in the timeslot that share a curriculum with the event
+    /// allocated at the allocation index. Each lecture is counted once, however many curricula it
+    /// shares.
+    pub fn curriculum_clashes(&self, allocation_index: (usize, usize)) -> usize {
+        let (timeslot_index, room_index) = allocation_index;
+        let event_index = match self.event_at(allocation_index) {
+            Some(event_index) => event_index,
+            None => return 0,
+        };
+
+        // The event itself is counted once in each of its curricula.
+        let curricula = &self.candidate.keys.curricula[event_index];
+        let others: usize = curricula.iter()
+            .map(|&curriculum| self.curriculum_lectures(timeslot_index, curriculum) - 1)
+            .sum();
+        if others == 0 || curricula.len() == 1 {
+            return others;
+        }
+
+        // A lecture sharing more than one curriculum would be counted more than once, so fall
+        // back to looking at each of them.
+        let mut num_clashes = 0;
+        for other_room_index in 0..self.num_rooms() {
+            if other_room_index == room_index {
+                continue;
+            }
+            if let Some(other_event_index) = self.event_at((timeslot_index, other_room_index)) {
+                let other_curricula = &self.candidate.keys.curricula[other_event_index];
+                if other_curricula.iter().any(|curriculum| curricula.contains(curriculum)) {
+                    num_clashes += 1;
+                }
+            }
+        }
+        num_clashes
+    }
+
+    /// The indices of the events of the course.
+    pub fn course_events(&self, course_id: &str) -> &'a [usize] {
+        self.candidate.keys.course_events.get(course_id).map_or(&[], |events| &events[..])
+    }
+
+    /// The indices of the events in the curriculum.
+    pub fn curriculum_events(&self, curriculum_id: &str) -> &'a [usize] {
+        self.candidate
+            .keys
+            .curriculum_events
+            .get(curriculum_id)
+            .map_or(&[], |events| &events[..])
+    }
+
+    /// The number of the curriculum, for curriculum_lectures(), or None if no event is in it.
+    pub fn curriculum_number(&self, curriculum_id: &str) -> Option<usize> {
+        self.candidate.keys.curriculum_numbers.get(curriculum_id).cloned()
+    }
+
+    /// The number of lectures the curriculum, by curriculum number, has in the timeslot.
+    pub fn curriculum_lectures(&self, timeslot_index: usize, curriculum: usize) -> usize {
+        let candidate = self.candidate;
+        let curricula = &candidate.keys.curricula;
+        let mut lectures = candidate.curriculum_lectures[(timeslot_index, curriculum)];
+        for &(cell, new_event_index) in self.changes.iter() {
+            if cell.0 != timeslot_index {
+                continue;
+            }
+            if candidate.event_at(cell).is_some_and(|old| curricula[old].contains(&curriculum)) {
+                lectures -= 1;
+            }
+            if new_event_index.is_some_and(|new| curricula[new].contains(&curriculum)) {
+                lectures += 1;
+            }
+        }
+        lectures
+    }
+
+    fn is_changed(&self, index: (usize, usize)) -> bool {
+        self.changes.iter().any(|&(cell, _)| cell == index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, StdRng};
+
+    use data;
+    use evaluation;
+    use super::*;
+
+    /// Checks the cost kept up to date by the candidate against evaluating it from scratch.
+    fn assert_cost_consistent(candidate: &CandidateSolution, instance: &Instance) {
+        assert_eq!(evaluation::evaluate(candidate, instance).total(), candidate.cost());
+        assert_eq!(candidate.clone().recompute_from_scratch(instance), candidate.cost());
+    }
+
+    fn random_cell<R: Rng>(rng: &mut R, instance: &Instance) -> (usize, usize) {
+        (rng.gen_range(0, instance.num_timeslots()), rng.gen_range(0, instance.num_rooms()))
+    }
+
+    #[test]
+    fn cost_matches_evaluation_after_random_moves() {
+        let mut instance = data::test_instance("random_moves");
+        for seed in 0..20 {
+            // Check the constraints that only some formulations have as well.
+            let formulation = if seed % 2 == 0 { Formulation::Ud2 } else { Formulation::Ud4 };
+            instance.set_formulation(formulation);
+            let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+            let mut candidate = CandidateSolution::new(&instance);
+            assert_cost_consistent(&candidate, &instance);
+
+            for _ in 0..200 {
+                let before = candidate.cost();
+                let expected = match rng.gen_range(0, 3) {
+                    0 => {
+                        let (timeslot_index, room_index) = random_cell(&mut rng, &instance);
+                        let event_index = if rng.gen_weighted_bool(4) {
+                            None
+                        } else {
+                            Some(rng.gen_range(0, instance.num_events()))
+                        };
+                        let delta = candidate.allocate_delta(timeslot_index,
+                                                             room_index,
+                                                             event_index,
+                                                             &instance);
+                        candidate.allocate_event(timeslot_index,
+                                                 room_index,
+                                                 event_index,
+                                                 &instance);
+                        Some(delta)
+                    }
+                    1 => {
+                        let a = random_cell(&mut rng, &instance);
+                        let b = random_cell(&mut rng, &instance);
+                        let delta = candidate.swap_delta(a, b, &instance);
+                        candidate.swap(a, b, &instance);
+                        Some(delta)
+                    }
+                    _ => {
+                        let event_index = rng.gen_range(0, instance.num_events());
+                        candidate.unallocate_event(event_index, &instance);
+                        None
+                    }
+                };
+                if let Some(delta) = expected {
+                    assert_eq!(delta, CostDelta::between(before, candidate.cost()));
+                }
+                assert_cost_consistent(&candidate, &instance);
+            }
+        }
+    }
+
+    #[test]
+    fn swap_delta_matches_cost_after_random_swaps() {
+        let mut instance = data::test_instance("random_swaps");
+        for seed in 0..10 {
+            let formulation = if seed % 2 == 0 { Formulation::Ud2 } else { Formulation::Ud4 };
+            instance.set_formulation(formulation);
+            let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+
+            // Allocate every event, so that most swaps move two of them.
+            let mut candidate = CandidateSolution::new(&instance);
+            for event_index in 0..instance.num_events() {
+                let mut cell = random_cell(&mut rng, &instance);
+                while candidate.get_allocation_with_index(cell).is_some() {
+                    cell = random_cell(&mut rng, &instance);
+                }
+                candidate.allocate_event(cell.0, cell.1, Some(event_index), &instance);
+            }
+
+            for _ in 0..500 {
+                let a = random_cell(&mut rng, &instance);
+                let b = random_cell(&mut rng, &instance);
+                let cost = candidate.cost();
+                let delta = candidate.swap_delta(a, b, &instance);
+                candidate.swap(a, b, &instance);
+                let after = candidate.cost();
+                assert_eq!(cost.hard as isize + delta.hard, after.hard as isize);
+                assert_eq!(cost.soft as isize + delta.soft, after.soft as isize);
+            }
+            assert_cost_consistent(&candidate, &instance);
+        }
+    }
 }
@@ -1,14 +1,234 @@
-use candidate::CandidateSolution;
-use data::Instance;
+use std::cmp;
+
+use candidate::Timetable;
+use data::{Instance, Event};
+
+/// Whether breaking a constraint makes a timetable infeasible, or just less desirable.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ConstraintKind {
+    Hard,
+    Soft,
+}
 
 /// A trait specifying the interface of a constraint.
 pub trait Constraint {
-    /// Returns the number of violations the allocation has for this constraint.
+    /// Returns the number of violations the allocation has for this constraint. Only the
+    /// allocations in the same timeslot may be looked at, as those are the only ones whose costs
+    /// are checked again when the timeslot changes.
     fn check_for_violations(&self,
-                            candidate: &CandidateSolution,
+                            timetable: &Timetable,
                             allocation_index: (usize, usize),
                             instance: &Instance)
                             -> usize;
+
+    /// The change in the violations of the allocations in the timeslot, other than those in the
+    /// changed cells, from the timetable before the cells changed to the one after. A constraint
+    /// that only looks at the allocation being checked leaves this as zero. Any other has to
+    /// override it, and has to be one of the instance's constraints rather than an event's.
+    fn check_timeslot_change(&self,
+                             _before: &Timetable,
+                             _after: &Timetable,
+                             _cells: &[(usize, usize)],
+                             _instance: &Instance)
+                             -> isize {
+        0
+    }
+
+    /// A short name for this constraint, for reporting.
+    fn name(&self) -> &str;
+
+    /// Whether this is a hard or a soft constraint.
+    fn kind(&self) -> ConstraintKind;
+
+    /// The penalty given for each violation of this constraint.
+    fn weight(&self) -> usize {
+        1
+    }
+}
+
+/// The part of a timetable that a constraint is evaluated over.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Scope {
+    /// A single (timeslot, room) allocation.
+    Cell,
+    /// Every lecture of a course.
+    Course,
+    /// Every lecture of every course in a curriculum.
+    Curriculum,
+    /// Every lecture taught by a teacher.
+    Teacher,
+    /// Every lecture on a day.
+    Day,
+    /// The whole solution.
+    Solution,
+}
+
+impl Scope {
+    /// Every group of this kind in the instance.
+    pub fn groups(&self, instance: &Instance) -> Vec<Group> {
+        match *self {
+            Scope::Cell => {
+                let mut groups = Vec::with_capacity(instance.num_timeslots() * instance.num_rooms());
+                for timeslot_index in 0..instance.num_timeslots() {
+                    for room_index in 0..instance.num_rooms() {
+                        groups.push(Group::Cell(timeslot_index, room_index));
+                    }
+                }
+                groups
+            }
+            Scope::Day => (0..instance.days()).map(Group::Day).collect(),
+            Scope::Solution => vec![Group::Solution],
+            Scope::Course | Scope::Curriculum | Scope::Teacher => {
+                let mut groups = Vec::new();
+                for event in instance.events() {
+                    for group in self.event_groups(event) {
+                        if !groups.contains(&group) {
+                            groups.push(group);
+                        }
+                    }
+                }
+                groups
+            }
+        }
+    }
+
+    /// The groups of this kind that the event belongs to, when it is allocated at the
+    /// allocation index.
+    pub fn groups_of(&self,
+                     event: &Event,
+                     allocation_index: (usize, usize),
+                     instance: &Instance)
+                     -> Vec<Group> {
+        let (timeslot_index, room_index) = allocation_index;
+        match *self {
+            Scope::Cell => vec![Group::Cell(timeslot_index, room_index)],
+            Scope::Day => {
+                let timeslot = instance.timeslot(timeslot_index).expect("Invalid timeslot index.");
+                vec![Group::Day(timeslot.day)]
+            }
+            Scope::Solution => vec![Group::Solution],
+            Scope::Course | Scope::Curriculum | Scope::Teacher => self.event_groups(event),
+        }
+    }
+
+    /// The groups of this kind that the event belongs to, wherever it is allocated.
+    fn event_groups(&self, event: &Event) -> Vec<Group> {
+        match *self {
+            Scope::Course => vec![Group::Course(event.course_id().to_owned())],
+            Scope::Curriculum => {
+                event.curriculum_ids()
+                    .iter()
+                    .map(|curriculum_id| Group::Curriculum(curriculum_id.clone()))
+                    .collect()
+            }
+            Scope::Teacher => vec![Group::Teacher(event.teacher().to_owned())],
+            Scope::Cell | Scope::Day | Scope::Solution => Vec::new(),
+        }
+    }
+}
+
+/// A specific group of events that a GroupConstraint is evaluated over.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Group {
+    /// The allocation at (timeslot index, room index).
+    Cell(usize, usize),
+    /// Every lecture of the course with this id.
+    Course(String),
+    /// Every lecture in the curriculum with this id.
+    Curriculum(String),
+    /// Every lecture taught by the teacher with this id.
+    Teacher(String),
+    /// Every lecture on this day.
+    Day(usize),
+    /// Every lecture in the solution.
+    Solution,
+}
+
+/// A constraint that can only be checked by looking at a group of events as a whole (eg. every
+/// lecture of a course), rather than a single allocation.
+pub trait GroupConstraint {
+    /// Returns the number of violations the group has for this constraint.
+    fn check_group(&self,
+                   timetable: &Timetable,
+                   group: &Group,
+                   instance: &Instance)
+                   -> usize;
+
+    /// The change in the violations of the group from the timetable before some cells changed
+    /// to the one after, or None if the group has to be checked again in full.
+    fn check_group_change(&self,
+                          _before: &Timetable,
+                          _after: &Timetable,
+                          _group: &Group,
+                          _cells: &[(usize, usize)],
+                          _instance: &Instance)
+                          -> Option<isize> {
+        None
+    }
+
+    /// The kind of group this constraint is evaluated over.
+    fn scope(&self) -> Scope;
+
+    /// A short name for this constraint, for reporting.
+    fn name(&self) -> &str;
+
+    /// Whether this is a hard or a soft constraint.
+    fn kind(&self) -> ConstraintKind;
+
+    /// The penalty given for each violation of this constraint.
+    fn weight(&self) -> usize {
+        1
+    }
+}
+
+/// The soft constraints a timetable is judged by, following the curriculum-based course
+/// timetabling formulations from the University of Udine.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Formulation {
+    /// Minimum working days, curriculum compactness and room stability.
+    #[default]
+    Ud2,
+    /// UD2, with the number of lectures each curriculum has on a day also kept within the
+    /// instance's daily lectures, and the lectures of courses with double lectures held in pairs.
+    Ud4,
+}
+
+impl Formulation {
+    /// Finds a formulation by its name ("ud2" or "ud4"), ignoring case.
+    pub fn from_name(name: &str) -> Option<Formulation> {
+        match name.to_lowercase().as_str() {
+            "ud2" => Some(Formulation::Ud2),
+            "ud4" => Some(Formulation::Ud4),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match *self {
+            Formulation::Ud2 => "ud2",
+            Formulation::Ud4 => "ud4",
+        }
+    }
+
+    /// The constraints on groups of events that this formulation checks.
+    pub fn group_constraints(&self) -> Vec<Box<dyn GroupConstraint>> {
+        let mut group_constraints: Vec<Box<dyn GroupConstraint>> =
+            vec![Box::new(MinWorkingDaysConstraint),
+                 Box::new(CurriculumCompactnessConstraint),
+                 Box::new(RoomStabilityConstraint)];
+        if *self == Formulation::Ud4 {
+            group_constraints.push(Box::new(DailyLecturesConstraint));
+        }
+        if self.has_double_lectures() {
+            group_constraints.push(Box::new(DoubleLecturesConstraint));
+        }
+        group_constraints
+    }
+
+    /// Returns true if the lectures of courses with double lectures should be held in pairs.
+    pub fn has_double_lectures(&self) -> bool {
+        *self == Formulation::Ud4
+    }
 }
 
 /// Allocation must be in one of the specified rooms.
@@ -22,21 +242,18 @@ impl RoomConstraint {
 
 impl Constraint for RoomConstraint {
     fn check_for_violations(&self,
-                            candidate: &CandidateSolution,
+                            timetable: &Timetable,
                             allocation_index: (usize, usize),
                             instance: &Instance)
                             -> usize {
-        let allocation = candidate.get_allocation_with_index(allocation_index);
         let mut violations = 0;
-        if let Some(allocation) = allocation {
-            let event = {
-                let event_index = allocation.event_index();
-                instance.event(event_index).unwrap()
-            };
+        if let Some(event_index) = timetable.event_at(allocation_index) {
+            let event = instance.event(event_index).unwrap();
+            let room_index = allocation_index.1;
 
             let is_valid_room = if let Some(()) = {
                 event.valid_rooms()
-                    .and_then(|rooms| if rooms.contains(&allocation.room_index()) {
+                    .and_then(|rooms| if rooms.contains(&room_index) {
                         Some(())
                     } else {
                         None
@@ -53,6 +270,14 @@ impl Constraint for RoomConstraint {
         }
         violations
     }
+
+    fn name(&self) -> &str {
+        "Rooms"
+    }
+
+    fn kind(&self) -> ConstraintKind {
+        ConstraintKind::Hard
+    }
 }
 
 /// The event must not be in one of the specified timeslots.
@@ -66,28 +291,33 @@ impl TimeSlotConstraint {
 
 impl Constraint for TimeSlotConstraint {
     fn check_for_violations(&self,
-                            candidate: &CandidateSolution,
+                            timetable: &Timetable,
                             allocation_index: (usize, usize),
                             instance: &Instance)
                             -> usize {
-        let allocation = candidate.get_allocation_with_index(allocation_index);
         let mut violations = 0;
-        if let Some(allocation) = allocation {
-            let event = {
-                let event_index = allocation.event_index();
-                instance.event(event_index).unwrap()
-            };
+        if let Some(event_index) = timetable.event_at(allocation_index) {
+            let event = instance.event(event_index).unwrap();
 
-            let timeslot = instance.timeslot(allocation.timeslot_index());
+            let timeslot = instance.timeslot(allocation_index.0);
             if event.banned_timeslots().contains(timeslot.unwrap()) {
                 violations = 1; // one violation!
             }
         }
         violations
     }
+
+    fn name(&self) -> &str {
+        "Unavailability"
+    }
+
+    fn kind(&self) -> ConstraintKind {
+        ConstraintKind::Hard
+    }
 }
 
-/// An event must be allocated in a room that will fit it's students.
+/// An event should be allocated in a room that will fit it's students. Each student over the
+/// capacity of the room counts as a violation.
 pub struct RoomCapacityConstraint;
 
 impl RoomCapacityConstraint {
@@ -98,23 +328,20 @@ impl RoomCapacityConstraint {
 
 impl Constraint for RoomCapacityConstraint {
     fn check_for_violations(&self,
-                            candidate: &CandidateSolution,
+                            timetable: &Timetable,
                             allocation_index: (usize, usize),
                             instance: &Instance)
                             -> usize {
-        let allocation = candidate.get_allocation_with_index(allocation_index);
         let mut violations = 0;
-        if let Some(allocation) = allocation {
-            let room = match instance.room(allocation.room_index()) {
+        if let Some(event_index) = timetable.event_at(allocation_index) {
+            let room = match instance.room(allocation_index.1) {
                 Some(room) => room,
                 None => panic!("Invalid room allocated to an allocation in RoomCapacityConstraint"),
             };
 
-            let event_index = allocation.event_index();
-
             if let Some(event) = instance.event(event_index) {
-                if room.capacity() > event.num_students() {
-                    violations = 1;
+                if event.num_students() > room.capacity() {
+                    violations = event.num_students() - room.capacity();
                 }
             } else {
                 panic!("Invalid event index found in an allocation. (in RoomCapacityConstraint)");
@@ -122,9 +349,17 @@ impl Constraint for RoomCapacityConstraint {
         }
         violations
     }
+
+    fn name(&self) -> &str {
+        "RoomCapacity"
+    }
+
+    fn kind(&self) -> ConstraintKind {
+        ConstraintKind::Soft
+    }
 }
 
-/// An event must not be allocated at the same time as another unit in its curriculum.
+/// An event must not be allocated at the same time as another unit in any of its curricula.
 pub struct CurriculumConstraint;
 
 impl CurriculumConstraint {
@@ -135,46 +370,46 @@ impl CurriculumConstraint {
 
 impl Constraint for CurriculumConstraint {
     fn check_for_violations(&self,
-                            candidate: &CandidateSolution,
+                            timetable: &Timetable,
                             allocation_index: (usize, usize),
-                            instance: &Instance)
+                            _instance: &Instance)
                             -> usize {
-        let allocation = candidate.get_allocation_with_index(allocation_index);
-        if let Some(allocation) = allocation {
-            // Timeslot is fixed.
-            let timeslot_index = allocation.timeslot_index();
-
-            let event = {
-                let event_index = allocation.event_index();
-                instance.event(event_index).expect("Invalid event_index specified.")
-            };
+        timetable.curriculum_clashes(allocation_index)
+    }
 
-            let mut num_matches = 0;
-            // Room is changable.
-            for room_index in 0..instance.num_rooms() {
-                // Get the allocation that we are currently checking against.
-                let check_against = candidate.get_allocation(timeslot_index, room_index);
-                // Is this allocation not empty?
-                if let Some(other_allocation) = check_against {
-                    // Get the other allocations' event from the instance.
-                    let other_event = {
-                        let event_index = other_allocation.event_index();
-                        instance.event(event_index).expect("Invalid event_index specified.")
-                    };
-
-                    // Check to see if this
-                    if other_event.curriculum_id() == event.curriculum_id() {
-                        num_matches += 1;
-                    }
-                }
+    fn check_timeslot_change(&self,
+                             before: &Timetable,
+                             after: &Timetable,
+                             cells: &[(usize, usize)],
+                             instance: &Instance)
+                             -> isize {
+        // Clashes go both ways, so the other allocations gain (or lose) a clash for each one
+        // that an event in a changed cell has with them.
+        let mut change = 0;
+        for &(timetable, sign) in [(after, 1), (before, -1)].iter() {
+            for &cell in cells.iter() {
+                let event = match timetable.event_at(cell) {
+                    Some(event_index) => instance.event(event_index).unwrap(),
+                    None => continue,
+                };
+                let changed_clashes = cells.iter()
+                    .filter(|&&other| other != cell)
+                    .filter_map(|&other| timetable.event_at(other))
+                    .filter(|&other| event.shares_curriculum_with(instance.event(other).unwrap()))
+                    .count();
+                let clashes = timetable.curriculum_clashes(cell) - changed_clashes;
+                change += sign * clashes as isize;
             }
-            // There should only be one match to this curriculum. Anything else is a violation.
-            // ie. the number of violations is:
-            num_matches - 1
-        } else {
-            // If this is an empty allocation, there are no violations...
-            0
         }
+        change
+    }
+
+    fn name(&self) -> &str {
+        "Conflicts"
+    }
+
+    fn kind(&self) -> ConstraintKind {
+        ConstraintKind::Hard
     }
 }
 
@@ -189,40 +424,432 @@ impl TeacherConstraint {
 
 impl Constraint for TeacherConstraint {
     fn check_for_violations(&self,
-                            candidate: &CandidateSolution,
+                            timetable: &Timetable,
                             allocation_index: (usize, usize),
-                            instance: &Instance)
+                            _instance: &Instance)
                             -> usize {
-        let allocation = candidate.get_allocation_with_index(allocation_index);
-        let mut matches = 0;
-        if let Some(allocation) = allocation {
-            let timeslot_index = allocation.timeslot_index();
-            let event = {
-                let event_index = allocation.event_index();
-                instance.event(event_index).expect("Invalid event_index provided.")
-            };
+        let matches = timetable.event_at(allocation_index)
+            .map_or(0, |event_index| timetable.teacher_lectures(allocation_index.0, event_index));
 
-            for room_index in 0..instance.num_rooms() {
-                if let Some(other_allocation) =
-                       candidate.get_allocation(timeslot_index, room_index) {
-                    let other_event = {
-                        let event_index = other_allocation.event_index();
-                        instance.event(event_index).expect("Invalid event_index provided.")
-                    };
+        // We expect to find one match (which is this course).
+        // If we find more than that, we have a violation.
+        if matches != 1 {
+            1
+        } else {
+            0
+        }
+    }
 
-                    if event.teacher() == other_event.teacher() {
-                        matches += 1;
-                    }
+    fn check_timeslot_change(&self,
+                             before: &Timetable,
+                             after: &Timetable,
+                             cells: &[(usize, usize)],
+                             instance: &Instance)
+                             -> isize {
+        let timeslot_index = cells[0].0;
+        let teacher = |event_index: usize| instance.event(event_index).unwrap().teacher();
+        let events = || {
+            cells.iter()
+                .flat_map(|&cell| before.event_at(cell).into_iter().chain(after.event_at(cell)))
+        };
+
+        let mut change = 0;
+        for (position, event_index) in events().enumerate() {
+            // Look at each teacher once.
+            if events().take(position).any(|other| teacher(other) == teacher(event_index)) {
+                continue;
+            }
+            let lectures = before.teacher_lectures(timeslot_index, event_index);
+            let changed_lectures = cells.iter()
+                .filter_map(|&cell| before.event_at(cell))
+                .filter(|&other| teacher(other) == teacher(event_index))
+                .count();
+            // The teacher's lectures outside the changed cells clash whenever the teacher has
+            // more than one lecture in the timeslot.
+            let unchanged = lectures - changed_lectures;
+            let clashed_before = (lectures > 1) as isize;
+            let clashed_after = (after.teacher_lectures(timeslot_index, event_index) > 1) as isize;
+            change += unchanged as isize * (clashed_after - clashed_before);
+        }
+        change
+    }
+
+    fn name(&self) -> &str {
+        "TeacherConflicts"
+    }
+
+    fn kind(&self) -> ConstraintKind {
+        ConstraintKind::Hard
+    }
+}
+
+/// The lectures of a course should be spread over a minimum number of days. Each day short of
+/// the minimum counts as a violation.
+pub struct MinWorkingDaysConstraint;
+
+impl GroupConstraint for MinWorkingDaysConstraint {
+    fn check_group(&self,
+                   timetable: &Timetable,
+                   group: &Group,
+                   instance: &Instance)
+                   -> usize {
+        let course_id = match *group {
+            Group::Course(ref course_id) => course_id,
+            _ => return 0,
+        };
+
+        let events = timetable.course_events(course_id);
+        let min_working_days = events.first()
+            .map_or(0, |&event_index| {
+                instance.event(event_index).expect("Invalid event_index.").min_working_days()
+            });
+
+        let mut days = Vec::new();
+        for &event_index in events.iter() {
+            if let Some((timeslot_index, _)) = timetable.allocation_index_of(event_index) {
+                let day = instance.timeslot(timeslot_index).expect("Invalid timeslot_index.").day;
+                if !days.contains(&day) {
+                    days.push(day);
                 }
             }
         }
 
-        // We expect to find one match (which is this course).
-        // If we find more than that, we have a violation.
-        if matches != 1 {
-            1
+        if days.len() < min_working_days {
+            min_working_days - days.len()
+        } else {
+            0
+        }
+    }
+
+    fn scope(&self) -> Scope {
+        Scope::Course
+    }
+
+    fn name(&self) -> &str {
+        "MinWorkingDays"
+    }
+
+    fn kind(&self) -> ConstraintKind {
+        ConstraintKind::Soft
+    }
+
+    fn weight(&self) -> usize {
+        5
+    }
+}
+
+/// The lectures of a curriculum should be kept together on each day. Each lecture without
+/// another lecture of the same curriculum in the period before or after it counts as a
+/// violation.
+pub struct CurriculumCompactnessConstraint;
+
+impl GroupConstraint for CurriculumCompactnessConstraint {
+    fn check_group(&self,
+                   timetable: &Timetable,
+                   group: &Group,
+                   instance: &Instance)
+                   -> usize {
+        let curriculum = match curriculum_number(timetable, group) {
+            Some(curriculum) => curriculum,
+            None => return 0,
+        };
+        let lectures = |timeslot_index| timetable.curriculum_lectures(timeslot_index, curriculum);
+        (0..timetable.num_timeslots())
+            .map(|timeslot_index| isolated_lectures(&lectures, timeslot_index, instance))
+            .sum()
+    }
+
+    fn check_group_change(&self,
+                          before: &Timetable,
+                          after: &Timetable,
+                          group: &Group,
+                          cells: &[(usize, usize)],
+                          instance: &Instance)
+                          -> Option<isize> {
+        let curriculum = match curriculum_number(before, group) {
+            Some(curriculum) => curriculum,
+            None => return Some(0),
+        };
+
+        let changes = lecture_changes(before, after, curriculum, cells);
+        let lectures_before = |index| before.curriculum_lectures(index, curriculum);
+        let lectures_after = |index| lectures_after(before, &changes, index, curriculum);
+
+        // Only the timeslots where the curriculum's lectures change, and the ones next to them on
+        // the same day, can change.
+        let periods_per_day = instance.periods_per_day();
+        let neighbours = |timeslot_index: usize| {
+            // NOTE: See Instance::to_timeslot_index() for how timeslots are laid out.
+            let day_start = timeslot_index - timeslot_index % periods_per_day;
+            let first = cmp::max(timeslot_index, day_start + 1) - 1;
+            let last = cmp::min(timeslot_index + 1, day_start + periods_per_day - 1);
+            first..last + 1
+        };
+        let mut change = 0;
+        for (position, &(changed_index, _)) in changes.iter().enumerate() {
+            for timeslot_index in neighbours(changed_index) {
+                // Look at each timeslot once.
+                let earlier = &changes[..position];
+                if earlier.iter().any(|&(other, _)| neighbours(other).contains(&timeslot_index)) {
+                    continue;
+                }
+                change += isolated_lectures(&lectures_after, timeslot_index, instance) as isize -
+                          isolated_lectures(&lectures_before, timeslot_index, instance) as isize;
+            }
+        }
+        Some(change)
+    }
+
+    fn scope(&self) -> Scope {
+        Scope::Curriculum
+    }
+
+    fn name(&self) -> &str {
+        "CurriculumCompactness"
+    }
+
+    fn kind(&self) -> ConstraintKind {
+        ConstraintKind::Soft
+    }
+
+    fn weight(&self) -> usize {
+        2
+    }
+}
+
+/// The number of the curriculum of the group, for Timetable::curriculum_lectures(), or None if
+/// the group isn't a curriculum with any lectures.
+fn curriculum_number(timetable: &Timetable, group: &Group) -> Option<usize> {
+    match *group {
+        Group::Curriculum(ref curriculum_id) => timetable.curriculum_number(curriculum_id),
+        _ => None,
+    }
+}
+
+/// Each changed timeslot where the number of lectures the curriculum has changes, with the
+/// number it has after the change.
+fn lecture_changes(before: &Timetable,
+                   after: &Timetable,
+                   curriculum: usize,
+                   cells: &[(usize, usize)])
+                   -> Vec<(usize, usize)> {
+    let mut changes: Vec<(usize, usize)> = Vec::new();
+    for &(timeslot_index, _) in cells.iter() {
+        if changes.iter().any(|&(other, _)| other == timeslot_index) {
+            continue;
+        }
+        let lectures = after.curriculum_lectures(timeslot_index, curriculum);
+        if lectures != before.curriculum_lectures(timeslot_index, curriculum) {
+            changes.push((timeslot_index, lectures));
+        }
+    }
+    changes
+}
+
+/// The lectures the curriculum has in the timeslot after the lecture changes. This saves looking
+/// through the changed cells every time the number in a timeslot is needed.
+fn lectures_after(before: &Timetable,
+                  changes: &[(usize, usize)],
+                  timeslot_index: usize,
+                  curriculum: usize)
+                  -> usize {
+    match changes.iter().find(|&&(other, _)| other == timeslot_index) {
+        Some(&(_, lectures)) => lectures,
+        None => before.curriculum_lectures(timeslot_index, curriculum),
+    }
+}
+
+/// The lectures the curriculum has in the timeslot if it has none in the periods either side of
+/// it on the same day, or else zero. The lectures it has in each timeslot come from lectures.
+fn isolated_lectures<F>(lectures: &F, timeslot_index: usize, instance: &Instance) -> usize
+    where F: Fn(usize) -> usize
+{
+    let num_lectures = lectures(timeslot_index);
+    if num_lectures == 0 {
+        return 0;
+    }
+
+    // NOTE: See Instance::to_timeslot_index() for how timeslots are laid out.
+    let period = timeslot_index % instance.periods_per_day();
+    let has_previous = period > 0 && lectures(timeslot_index - 1) > 0;
+    let has_next = period + 1 < instance.periods_per_day() && lectures(timeslot_index + 1) > 0;
+    if has_previous || has_next { 0 } else { num_lectures }
+}
+
+/// Every lecture of a course should be held in the same room. Each room used by the course
+/// beyond the first counts as a violation.
+pub struct RoomStabilityConstraint;
+
+impl GroupConstraint for RoomStabilityConstraint {
+    fn check_group(&self,
+                   timetable: &Timetable,
+                   group: &Group,
+                   _instance: &Instance)
+                   -> usize {
+        let course_id = match *group {
+            Group::Course(ref course_id) => course_id,
+            _ => return 0,
+        };
+
+        let mut rooms = Vec::new();
+        for &event_index in timetable.course_events(course_id).iter() {
+            if let Some((_, room_index)) = timetable.allocation_index_of(event_index) {
+                if !rooms.contains(&room_index) {
+                    rooms.push(room_index);
+                }
+            }
+        }
+
+        if rooms.len() > 1 {
+            rooms.len() - 1
         } else {
             0
         }
     }
+
+    fn scope(&self) -> Scope {
+        Scope::Course
+    }
+
+    fn name(&self) -> &str {
+        "RoomStability"
+    }
+
+    fn kind(&self) -> ConstraintKind {
+        ConstraintKind::Soft
+    }
+}
+
+/// The number of lectures a curriculum has on each day should be within the instance's daily
+/// lectures. Each lecture below the minimum or above the maximum counts as a violation. Days
+/// without any lectures are not held to the minimum.
+pub struct DailyLecturesConstraint;
+
+impl GroupConstraint for DailyLecturesConstraint {
+    fn check_group(&self,
+                   timetable: &Timetable,
+                   group: &Group,
+                   instance: &Instance)
+                   -> usize {
+        let curriculum = match curriculum_number(timetable, group) {
+            Some(curriculum) => curriculum,
+            None => return 0,
+        };
+        let lectures = |timeslot_index| timetable.curriculum_lectures(timeslot_index, curriculum);
+        (0..instance.days()).map(|day| day_violations(&lectures, day, instance)).sum()
+    }
+
+    fn check_group_change(&self,
+                          before: &Timetable,
+                          after: &Timetable,
+                          group: &Group,
+                          cells: &[(usize, usize)],
+                          instance: &Instance)
+                          -> Option<isize> {
+        let curriculum = match curriculum_number(before, group) {
+            Some(curriculum) => curriculum,
+            None => return Some(0),
+        };
+
+        let changes = lecture_changes(before, after, curriculum, cells);
+        let lectures_before = |index| before.curriculum_lectures(index, curriculum);
+        let lectures_after = |index| lectures_after(before, &changes, index, curriculum);
+
+        // Only the days where the curriculum's lectures change can change.
+        let day_of = |timeslot_index| {
+            instance.timeslot(timeslot_index).expect("Invalid timeslot_index.").day
+        };
+        let mut change = 0;
+        for (position, &(timeslot_index, _)) in changes.iter().enumerate() {
+            let day = day_of(timeslot_index);
+            // Look at each day once.
+            if changes[..position].iter().any(|&(other, _)| day_of(other) == day) {
+                continue;
+            }
+            change += day_violations(&lectures_after, day, instance) as isize -
+                      day_violations(&lectures_before, day, instance) as isize;
+        }
+        Some(change)
+    }
+
+    fn scope(&self) -> Scope {
+        Scope::Curriculum
+    }
+
+    fn name(&self) -> &str {
+        "DailyLectures"
+    }
+
+    fn kind(&self) -> ConstraintKind {
+        ConstraintKind::Soft
+    }
+}
+
+/// The lectures the curriculum has below the minimum or above the maximum daily lectures on the
+/// day. A day without any lectures is not held to the minimum. The lectures it has in each
+/// timeslot come from lectures.
+fn day_violations<F>(lectures: &F, day: usize, instance: &Instance) -> usize
+    where F: Fn(usize) -> usize
+{
+    let num_lectures: usize = (0..instance.periods_per_day())
+        .map(|period| lectures(instance.to_timeslot_index(day, period)))
+        .sum();
+    let (min, max) = instance.daily_lectures();
+    if num_lectures > 0 && num_lectures < min {
+        min - num_lectures
+    } else {
+        num_lectures.saturating_sub(max)
+    }
+}
+
+/// The lectures of a course with double lectures should be held in pairs, in consecutive periods
+/// of the same day and in the same room. Each pair that has been split up counts as a violation.
+pub struct DoubleLecturesConstraint;
+
+impl GroupConstraint for DoubleLecturesConstraint {
+    fn check_group(&self,
+                   timetable: &Timetable,
+                   group: &Group,
+                   instance: &Instance)
+                   -> usize {
+        let course_id = match *group {
+            Group::Course(ref course_id) => course_id,
+            _ => return 0,
+        };
+
+        let mut violations = 0;
+        for &event_index in timetable.course_events(course_id).iter() {
+            let event = instance.event(event_index).expect("Invalid event_index.");
+            // NOTE: Each pair is checked from its first event only, so that it is counted once.
+            // A pair is only split up once both of its events have been allocated.
+            let partner_index = match event.double_lecture_partner() {
+                Some(partner_index) if partner_index > event_index => partner_index,
+                _ => continue,
+            };
+            let allocations = (timetable.allocation_index_of(event_index),
+                               timetable.allocation_index_of(partner_index));
+            if let (Some((event_timeslot, event_room)), Some((partner_timeslot, partner_room))) =
+                   allocations {
+                let together = event_room == partner_room &&
+                               (instance.are_consecutive(event_timeslot, partner_timeslot) ||
+                                instance.are_consecutive(partner_timeslot, event_timeslot));
+                if !together {
+                    violations += 1;
+                }
+            }
+        }
+        violations
+    }
+
+    fn scope(&self) -> Scope {
+        Scope::Course
+    }
+
+    fn name(&self) -> &str {
+        "DoubleLectures"
+    }
+
+    fn kind(&self) -> ConstraintKind {
+        ConstraintKind::Soft
+    }
 }
@@ -0,0 +1,608 @@
+//! Instances as a directory of CSV files, for editing in a spreadsheet. Each file starts with a
+//! row of headings, and its columns can come in any order:
+//!
+//! ```text
+//! instance.csv            key,value  (name, days, periods_per_day, min_daily_lectures and
+//!                                     max_daily_lectures)
+//! courses.csv             course,teacher,lectures,min_days,students,double_lectures
+//! rooms.csv               room,capacity,building
+//! curricula.csv           curriculum,course  (a row for each course of each curriculum)
+//! unavailabilities.csv    course,day,period,lecture
+//! room_constraints.csv    course,room,lecture  (a row for each room that is unsuitable for the
+//!                                               course)
+//! ```
+//!
+//! A row of the last two files bans a single lecture of the course if its lecture column has the
+//! number of the lecture (counting from 0), or every lecture of the course if it is blank.
+//!
+//! The daily lectures, the double_lectures, building and lecture columns, and the last three
+//! files can be left out. Solutions are written as a single file with a row for each allocation.
+
+use std;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use candidate::CandidateSolution;
+use data::{Instance, Event, Curriculum, TimetableDataResult, Room, TimeSlot, DataError,
+           InstanceFormat};
+
+const INSTANCE_FILE: &str = "instance.csv";
+const COURSES_FILE: &str = "courses.csv";
+const ROOMS_FILE: &str = "rooms.csv";
+const CURRICULA_FILE: &str = "curricula.csv";
+const UNAVAILABILITIES_FILE: &str = "unavailabilities.csv";
+const ROOM_CONSTRAINTS_FILE: &str = "room_constraints.csv";
+
+/// Instances written by `save_instance`, found by the path of their instance.csv.
+pub struct CsvFormat;
+
+impl InstanceFormat for CsvFormat {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv"]
+    }
+
+    fn sniff(&self, head: &str) -> bool {
+        let head = head.trim_start_matches('\u{feff}');
+        head.lines().next().is_some_and(|line| line.trim().to_lowercase() == "key,value")
+    }
+
+    fn load(&self, path: &str) -> TimetableDataResult {
+        load_instance(path)
+    }
+}
+
+/// Write the instance into the directory as CSV files, creating the directory if need be.
+pub fn save_instance(directory: &str, instance: &Instance) -> std::io::Result<()> {
+    let directory = Path::new(directory);
+    std::fs::create_dir_all(directory)?;
+
+    // The events of each course, in the order the courses first appear.
+    let mut courses: Vec<(&str, Vec<usize>)> = Vec::new();
+    for (event_index, event) in instance.events().iter().enumerate() {
+        match courses.iter().position(|&(course_id, _)| course_id == event.course_id()) {
+            Some(index) => courses[index].1.push(event_index),
+            None => courses.push((event.course_id(), vec![event_index])),
+        }
+    }
+
+    let mut file = create(&directory.join(INSTANCE_FILE))?;
+    let (min, max) = instance.daily_lectures();
+    write_record(&mut file, &["key", "value"])?;
+    write_record(&mut file, &["name", instance.name().unwrap_or("")])?;
+    write_record(&mut file, &["days", &instance.days().to_string()])?;
+    write_record(&mut file,
+                 &["periods_per_day", &instance.periods_per_day().to_string()])?;
+    write_record(&mut file, &["min_daily_lectures", &min.to_string()])?;
+    write_record(&mut file, &["max_daily_lectures", &max.to_string()])?;
+
+    let mut file = create(&directory.join(COURSES_FILE))?;
+    write_record(&mut file,
+                 &["course", "teacher", "lectures", "min_days", "students", "double_lectures"])?;
+    for &(course_id, ref events) in courses.iter() {
+        let event = &instance.events()[events[0]];
+        let double_lectures = events.iter()
+            .any(|&event_index| instance.events()[event_index].double_lecture_partner().is_some());
+        write_record(&mut file,
+                     &[course_id,
+                       event.teacher(),
+                       &events.len().to_string(),
+                       &event.min_working_days().to_string(),
+                       &event.num_students().to_string(),
+                       if double_lectures { "1" } else { "0" }])?;
+    }
+
+    let mut file = create(&directory.join(ROOMS_FILE))?;
+    write_record(&mut file, &["room", "capacity", "building"])?;
+    for room in instance.rooms() {
+        write_record(&mut file, &[room.id(), &room.capacity().to_string(), room.building()])?;
+    }
+
+    let mut file = create(&directory.join(CURRICULA_FILE))?;
+    write_record(&mut file, &["curriculum", "course"])?;
+    for curriculum in instance.curricula() {
+        for course_id in curriculum.course_ids() {
+            write_record(&mut file, &[curriculum.id(), course_id])?;
+        }
+    }
+
+    let mut file = create(&directory.join(UNAVAILABILITIES_FILE))?;
+    write_record(&mut file, &["course", "day", "period", "lecture"])?;
+    for &(course_id, ref events) in courses.iter() {
+        let mut timeslots: Vec<TimeSlot> = Vec::new();
+        for &event_index in events.iter() {
+            for &timeslot in instance.events()[event_index].banned_timeslots() {
+                if !timeslots.contains(&timeslot) {
+                    timeslots.push(timeslot);
+                }
+            }
+        }
+        for timeslot in timeslots {
+            let lectures = banned_lectures(events, instance, |event| {
+                event.banned_timeslots().contains(&timeslot)
+            });
+            for lecture in lectures {
+                write_record(&mut file,
+                             &[course_id,
+                               &timeslot.day.to_string(),
+                               &timeslot.period.to_string(),
+                               &lecture])?;
+            }
+        }
+    }
+
+    let mut file = create(&directory.join(ROOM_CONSTRAINTS_FILE))?;
+    write_record(&mut file, &["course", "room", "lecture"])?;
+    for &(course_id, ref events) in courses.iter() {
+        for (room_index, room) in instance.rooms().iter().enumerate() {
+            let lectures = banned_lectures(events, instance, |event| {
+                event.valid_rooms().is_some_and(|rooms| !rooms.contains(&room_index))
+            });
+            for lecture in lectures {
+                write_record(&mut file, &[course_id, room.id(), &lecture])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The lecture column of each row to write for a ban, given the events of the course and whether
+/// each of them is banned. This is a single blank cell if every lecture is banned, or else the
+/// number of each lecture that is.
+fn banned_lectures<F>(events: &[usize], instance: &Instance, is_banned: F) -> Vec<String>
+    where F: Fn(&Event) -> bool
+{
+    let lectures: Vec<usize> = events.iter()
+        .enumerate()
+        .filter(|&(_, &event_index)| is_banned(&instance.events()[event_index]))
+        .map(|(lecture, _)| lecture)
+        .collect();
+    if lectures.len() == events.len() {
+        vec![String::new()]
+    } else {
+        lectures.iter().map(|lecture| lecture.to_string()).collect()
+    }
+}
+
+/// Write every allocation of the candidate to a CSV file, along with its cost.
+pub fn save_solution(path: &str,
+                     candidate: &CandidateSolution,
+                     instance: &Instance)
+                     -> std::io::Result<()> {
+    let mut file = create(Path::new(path))?;
+    write_record(&mut file,
+                 &["event", "course", "room", "day", "period", "hard", "soft"])?;
+
+    let mut allocations = candidate.allocations();
+    allocations.sort_by_key(|allocation| allocation.event_index());
+    for allocation in allocations {
+        let event = instance.event(allocation.event_index())
+            .expect("Invalid event index found in an allocation.");
+        let room = instance.room(allocation.room_index())
+            .expect("Invalid room index found in an allocation.");
+        let timeslot = instance.timeslot(allocation.timeslot_index())
+            .expect("Invalid timeslot index found in an allocation.");
+        let cost = allocation.cost();
+        write_record(&mut file,
+                     &[&allocation.event_index().to_string(),
+                       event.course_id(),
+                       room.id(),
+                       &timeslot.day.to_string(),
+                       &timeslot.period.to_string(),
+                       &cost.hard.to_string(),
+                       &cost.soft.to_string()])?;
+    }
+    Ok(())
+}
+
+/// Load an instance from the CSV files in a directory. The path can be the directory, or any
+/// file in it.
+pub fn load_instance(path: &str) -> TimetableDataResult {
+    let path = Path::new(path);
+    let directory = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(Path::new("."))
+    };
+    let mut instance = Instance::new();
+
+    read_instance_file(&mut instance, &Table::read(&directory.join(INSTANCE_FILE))?)?;
+
+    let courses = Table::read(&directory.join(COURSES_FILE))?;
+    for row in courses.rows() {
+        let course_id = row.get("course")?;
+        if instance.events().iter().any(|event| event.course_id() == course_id) {
+            return Err(row.error("course", "a course id that is not already used"));
+        }
+        let teacher_id = row.get("teacher")?;
+        let num_lectures: usize = row.parse("lectures", "a number of lectures")?;
+        let min_days: usize = row.parse("min_days", "a number of days")?;
+        let num_students: usize = row.parse("students", "a number of students")?;
+        let double_lectures = if courses.has_column("double_lectures") {
+            match row.get("double_lectures")?.to_lowercase().as_ref() {
+                "" | "0" | "no" | "false" => false,
+                "1" | "yes" | "true" => true,
+                _ => return Err(row.error("double_lectures", "0 or 1")),
+            }
+        } else {
+            false
+        };
+        instance.add_course(course_id,
+                            teacher_id,
+                            num_lectures,
+                            min_days,
+                            num_students,
+                            double_lectures);
+    }
+
+    let rooms = Table::read(&directory.join(ROOMS_FILE))?;
+    for row in rooms.rows() {
+        let room_id = row.get("room")?.to_owned();
+        let capacity: usize = row.parse("capacity", "the number of seats in the room")?;
+        let building = if rooms.has_column("building") {
+            row.get("building")?.to_owned()
+        } else {
+            String::new()
+        };
+        instance.add_room(Room::new(room_id, capacity, building));
+    }
+
+    if let Some(curricula) = Table::read_optional(&directory.join(CURRICULA_FILE))? {
+        // The courses of each curriculum, in the order the curricula first appear.
+        let mut course_ids: Vec<(String, Vec<String>)> = Vec::new();
+        for row in curricula.rows() {
+            let curriculum_id = row.get("curriculum")?;
+            let course_id = row.course(&instance)?;
+            for event in instance.mut_events_with_course_id(course_id) {
+                event.add_curriculum_id(curriculum_id.to_owned());
+            }
+            match course_ids.iter().position(|(other, _)| other == curriculum_id) {
+                Some(index) => course_ids[index].1.push(course_id.to_owned()),
+                None => course_ids.push((curriculum_id.to_owned(), vec![course_id.to_owned()])),
+            }
+        }
+        for (curriculum_id, course_ids) in course_ids {
+            instance.add_curriculum(Curriculum::new(curriculum_id, course_ids));
+        }
+    }
+
+    if let Some(unavailabilities) = Table::read_optional(&directory.join(UNAVAILABILITIES_FILE))? {
+        for row in unavailabilities.rows() {
+            let course_id = row.course(&instance)?;
+            let day: usize = row.parse("day", "a day")?;
+            if day >= instance.days() {
+                return Err(row.error("day", "a day within the instance"));
+            }
+            let period: usize = row.parse("period", "a period")?;
+            if period >= instance.periods_per_day() {
+                return Err(row.error("period", "a period within the instance"));
+            }
+            for event in row.lectures(course_id, &mut instance)? {
+                event.ban_timeslot(TimeSlot::new(day, period));
+            }
+        }
+    }
+
+    if let Some(room_constraints) = Table::read_optional(&directory.join(ROOM_CONSTRAINTS_FILE))? {
+        // As in the ectt ROOM_CONSTRAINTS, each row bans the course from the room.
+        let num_rooms = instance.num_rooms();
+        for row in room_constraints.rows() {
+            let course_id = row.course(&instance)?;
+            let room_id = row.get("room")?;
+            let room_index = match instance.rooms().iter().position(|room| room.id() == room_id) {
+                Some(room_index) => room_index,
+                None => return Err(row.error("room", "a room id from rooms.csv")),
+            };
+            for event in row.lectures(course_id, &mut instance)? {
+                event.ban_room(room_index, num_rooms);
+            }
+        }
+    }
+
+    Ok(instance)
+}
+
+/// Read the name, days and periods, and daily lectures of the instance.
+fn read_instance_file(instance: &mut Instance, table: &Table) -> Result<(), DataError> {
+    let mut name = None;
+    let mut days = None;
+    let mut periods = None;
+    let mut daily_lectures = instance.daily_lectures();
+    for row in table.rows() {
+        match row.get("key")?.to_lowercase().as_ref() {
+            "name" => name = Some(row.get("value")?.to_owned()),
+            "days" => days = Some(row.parse("value", "a number of days")?),
+            "periods_per_day" => periods = Some(row.parse("value", "a number of periods")?),
+            "min_daily_lectures" => daily_lectures.0 = row.parse("value", "a number of lectures")?,
+            "max_daily_lectures" => daily_lectures.1 = row.parse("value", "a number of lectures")?,
+            _ => return Err(row.error("key", "a known key")),
+        }
+    }
+
+    let missing = |key: &str| {
+        DataError::Csv {
+            path: table.path.clone(),
+            row: table.end_row,
+            column: "key".to_owned(),
+            expected: format!("a \"{}\" row", key),
+            found: "the end of the file".to_owned(),
+        }
+    };
+    let days = days.ok_or_else(|| missing("days"))?;
+    let periods = periods.ok_or_else(|| missing("periods_per_day"))?;
+    if let Some(name) = name {
+        if !name.is_empty() {
+            instance.set_name(name);
+        }
+    }
+    instance.set_timeslots(days, periods);
+    instance.set_daily_lectures(daily_lectures);
+    Ok(())
+}
+
+/// A CSV file, split into its headings and rows of cells.
+struct Table {
+    path: String,
+    headings: Vec<String>,
+    /// The cells of each row, along with its row number.
+    rows: Vec<(usize, Vec<String>)>,
+    /// The number of the last row of the file.
+    end_row: usize,
+}
+
+impl Table {
+    fn read(path: &Path) -> Result<Table, DataError> {
+        let display_path = path.to_string_lossy().into_owned();
+        let mut contents = String::new();
+        std::fs::File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|error| {
+                DataError::Io {
+                    path: display_path.clone(),
+                    error,
+                }
+            })?;
+
+        let mut rows = parse_records(&display_path, contents.trim_start_matches('\u{feff}'))?;
+        let end_row = rows.last().map_or(1, |&(row, _)| row);
+        if rows.is_empty() {
+            return Err(DataError::Csv {
+                path: display_path,
+                row: 1,
+                column: "1".to_owned(),
+                expected: "a row of headings".to_owned(),
+                found: "an empty file".to_owned(),
+            });
+        }
+        let headings = rows.remove(0)
+            .1
+            .iter()
+            .map(|heading| heading.trim().to_lowercase())
+            .collect();
+        Ok(Table {
+            path: display_path,
+            headings,
+            rows,
+            end_row,
+        })
+    }
+
+    /// Read a file that can be left out.
+    fn read_optional(path: &Path) -> Result<Option<Table>, DataError> {
+        if path.exists() {
+            Table::read(path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn has_column(&self, heading: &str) -> bool {
+        self.headings.iter().any(|other| other == heading)
+    }
+
+    fn rows(&self) -> Vec<Row<'_>> {
+        self.rows
+            .iter()
+            .map(|&(row, ref cells)| {
+                Row {
+                    table: self,
+                    row,
+                    cells,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A row of a CSV file, whose cells are found by the heading of their column.
+struct Row<'a> {
+    table: &'a Table,
+    row: usize,
+    cells: &'a [String],
+}
+
+impl<'a> Row<'a> {
+    /// The trimmed text of the cell in the column.
+    fn get(&self, heading: &str) -> Result<&'a str, DataError> {
+        match self.table.headings.iter().position(|other| other == heading) {
+            Some(column) => Ok(self.cells.get(column).map_or("", |cell| cell.trim())),
+            None => {
+                Err(DataError::Csv {
+                    path: self.table.path.clone(),
+                    row: 1,
+                    column: heading.to_owned(),
+                    expected: format!("a \"{}\" column", heading),
+                    found: self.table.headings.join(","),
+                })
+            }
+        }
+    }
+
+    /// Parse the cell in the column into a value.
+    fn parse<T: FromStr>(&self, heading: &str, expected: &str) -> Result<T, DataError> {
+        self.get(heading)?.parse().map_err(|_| self.error(heading, expected))
+    }
+
+    /// The course in the "course" column, making sure it is in courses.csv.
+    fn course(&self, instance: &Instance) -> Result<&'a str, DataError> {
+        let course_id = self.get("course")?;
+        if !instance.events().iter().any(|event| event.course_id() == course_id) {
+            return Err(self.error("course", "a course id from courses.csv"));
+        }
+        Ok(course_id)
+    }
+
+    /// The events of the course the row is about. This is the lecture in the "lecture" column,
+    /// or every lecture of the course if the column is blank or left out.
+    fn lectures<'b>(&self,
+                    course_id: &str,
+                    instance: &'b mut Instance)
+                    -> Result<Vec<&'b mut Event>, DataError> {
+        let mut events = instance.mut_events_with_course_id(course_id);
+        if !self.table.has_column("lecture") || self.get("lecture")?.is_empty() {
+            return Ok(events);
+        }
+        let expected = "the number of a lecture of the course";
+        let lecture: usize = self.parse("lecture", expected)?;
+        if lecture >= events.len() {
+            return Err(self.error("lecture", expected));
+        }
+        Ok(vec![events.swap_remove(lecture)])
+    }
+
+    /// Build an error for the cell in the column.
+    fn error(&self, heading: &str, expected: &str) -> DataError {
+        let found = self.get(heading).unwrap_or("");
+        DataError::Csv {
+            path: self.table.path.clone(),
+            row: self.row,
+            column: heading.to_owned(),
+            expected: expected.to_owned(),
+            found: found.to_owned(),
+        }
+    }
+}
+
+/// Split the contents of a CSV file into records of cells, each along with the line it starts
+/// on. Cells can be quoted, with "" for a quote inside a quoted cell. Blank lines are skipped.
+fn parse_records(path: &str, contents: &str) -> Result<Vec<(usize, Vec<String>)>, DataError> {
+    let mut records = Vec::new();
+    let mut chars = contents.chars().peekable();
+    let mut line = 1;
+    loop {
+        let start_line = line;
+        let mut cells = Vec::new();
+        let mut cell = String::new();
+        let mut ended = false;
+        loop {
+            match chars.next() {
+                None => {
+                    ended = true;
+                    break;
+                }
+                Some('\n') => {
+                    line += 1;
+                    break;
+                }
+                Some('\r') => {}
+                Some(',') => cells.push(std::mem::take(&mut cell)),
+                Some('"') if cell.trim().is_empty() => {
+                    cell.clear();
+                    loop {
+                        match chars.next() {
+                            None => {
+                                return Err(DataError::Csv {
+                                    path: path.to_owned(),
+                                    row: start_line,
+                                    column: (cells.len() + 1).to_string(),
+                                    expected: "a closing quote".to_owned(),
+                                    found: "the end of the file".to_owned(),
+                                })
+                            }
+                            Some('"') => {
+                                if chars.peek() == Some(&'"') {
+                                    chars.next();
+                                    cell.push('"');
+                                } else {
+                                    break;
+                                }
+                            }
+                            Some(c) => {
+                                if c == '\n' {
+                                    line += 1;
+                                }
+                                cell.push(c);
+                            }
+                        }
+                    }
+                }
+                Some(c) => cell.push(c),
+            }
+        }
+        cells.push(cell);
+
+        if cells.len() > 1 || !cells[0].trim().is_empty() {
+            records.push((start_line, cells));
+        }
+        if ended {
+            return Ok(records);
+        }
+    }
+}
+
+fn create(path: &Path) -> std::io::Result<std::io::BufWriter<std::fs::File>> {
+    std::fs::File::create(path).map(std::io::BufWriter::new)
+}
+
+/// Write a row of cells, quoting any that need it.
+fn write_record<W: Write>(writer: &mut W, cells: &[&str]) -> std::io::Result<()> {
+    let cells: Vec<String> = cells.iter()
+        .map(|&cell| {
+            let needs_quotes = cell.contains([',', '"', '\n', '\r']) ||
+                               cell.trim() != cell;
+            if needs_quotes {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_owned()
+            }
+        })
+        .collect();
+    write!(writer, "{}\r\n", cells.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use data::{self, TimeSlot};
+    use super::*;
+
+    #[test]
+    fn bans_on_a_single_lecture_round_trip() {
+        let mut instance = data::test_instance("csv_bans");
+        let num_rooms = instance.num_rooms();
+        instance.mut_events_with_course_id("C0")[2].ban_timeslot(TimeSlot::new(1, 2));
+        instance.mut_events_with_course_id("C0")[2].ban_room(1, num_rooms);
+        instance.mut_events_with_course_id("C3")[1].ban_room(1, num_rooms);
+
+        let directory = std::env::temp_dir()
+            .join(format!("time_table_{}_csv_bans", std::process::id()));
+        let directory = directory.to_str().expect("Temporary path is not valid unicode.");
+        save_instance(directory, &instance).unwrap();
+        let loaded = load_instance(directory);
+        let _ = std::fs::remove_dir_all(directory);
+        let loaded = loaded.unwrap();
+
+        assert_eq!(instance.num_events(), loaded.num_events());
+        for (event, other) in instance.events().iter().zip(loaded.events()) {
+            assert_eq!(event.course_id(), other.course_id());
+            assert!(event.banned_timeslots() == other.banned_timeslots());
+            assert_eq!(event.valid_rooms(), other.valid_rooms());
+        }
+        let c0 = loaded.events().iter().filter(|event| event.course_id() == "C0");
+        let banned: Vec<bool> = c0.map(|event| event.valid_rooms().is_some()).collect();
+        assert_eq!(banned, [false, false, true]);
+    }
+}
@@ -0,0 +1,25 @@
+use data::{TimetableDataResult, InstanceFormat};
+use data::ectt::{Loader, Dialect};
+
+/// The original curriculum-based course timetabling format (ITC-2007 track 3), from the Udine
+/// site. Its layout is close enough to the extended format that the same loader reads both.
+pub struct CttFormat;
+
+impl InstanceFormat for CttFormat {
+    fn name(&self) -> &str {
+        "ctt"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ctt"]
+    }
+
+    fn sniff(&self, head: &str) -> bool {
+        // Only the ctt header counts its unavailability constraints with "Constraints:".
+        head.lines().any(|line| line.trim().to_lowercase().starts_with("constraints:"))
+    }
+
+    fn load(&self, path: &str) -> TimetableDataResult {
+        Loader::with_dialect(path, Dialect::Ctt)?.load()
+    }
+}
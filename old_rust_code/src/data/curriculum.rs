@@ -0,0 +1,28 @@
+pub type CurriculumID = String;
+
+/// A group of courses that share students, and so must not be taught at the same time.
+pub struct Curriculum {
+    id: CurriculumID,
+    course_ids: Vec<String>,
+}
+
+impl Curriculum {
+    pub fn new(id: CurriculumID, course_ids: Vec<String>) -> Self {
+        Curriculum {
+            id,
+            course_ids,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn course_ids(&self) -> &[String] {
+        &self.course_ids
+    }
+
+    pub fn contains_course(&self, course_id: &str) -> bool {
+        self.course_ids.iter().any(|id| id == course_id)
+    }
+}
@@ -1,12 +1,75 @@
 use std;
+use std::str::FromStr;
 
-use data::{Instance, Event, TimetableDataResult, Room, TimeSlot, RoomConstraint,
-           TimeSlotConstraint};
+use data::{Instance, Curriculum, TimetableDataResult, Room, TimeSlot, TimeSlotConstraint,
+           DataError, InstanceFormat};
 
+// The sections of the file, as named in errors.
+const HEADER: &str = "header";
+const COURSES: &str = "COURSES";
+const ROOMS: &str = "ROOMS";
+const CURRICULA: &str = "CURRICULA";
+const UNAVAILABILITY_CONSTRAINTS: &str = "UNAVAILABILITY_CONSTRAINTS";
+const ROOM_CONSTRAINTS: &str = "ROOM_CONSTRAINTS";
+
+/// A line of the file, along with its line number.
+type Line = (usize, String);
+
+/// The extended curriculum-based course timetabling format, from the Udine site.
+pub struct EcttFormat;
+
+impl InstanceFormat for EcttFormat {
+    fn name(&self) -> &str {
+        "ectt"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ectt"]
+    }
+
+    fn sniff(&self, head: &str) -> bool {
+        // Only the ectt header has the daily lectures and separate unavailability constraints.
+        head.lines().any(|line| {
+            let line = line.trim().to_lowercase();
+            line.starts_with("min_max_daily_lectures:") ||
+            line.starts_with("unavailabilityconstraints:")
+        })
+    }
+
+    fn load(&self, path: &str) -> TimetableDataResult {
+        Loader::new(path)?.load()
+    }
+}
+
+/// The variants of the curriculum-based course timetabling format, which share most of their
+/// layout.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Dialect {
+    /// The extended format (.ectt).
+    Ectt,
+    /// The original format (.ctt). It has no daily lectures, double lectures, buildings or room
+    /// constraints, and counts the unavailability constraints with a "Constraints:" header key.
+    Ctt,
+}
+
+/// Loads an instance from an ectt (or ctt) file.
+///
+/// The header fields can come in any order, and each section is found by its title
+/// ("COURSES:", "ROOMS:", ...) rather than by its position in the file. Blank lines, '#'
+/// comments and CRLF line endings are ignored. The number of entries in each section is checked
+/// against the counts declared in the header. Each room constraint names a room that is unsuitable
+/// for the course, which is banned from it.
 pub struct Loader {
     instance: Instance,
 
-    lines: std::io::Lines<std::io::BufReader<std::fs::File>>,
+    path: String,
+    dialect: Dialect,
+    /// The lines of the header, up until the first section title.
+    header: Vec<Line>,
+    /// The lines of each section (without the title line) and the line the section started on.
+    sections: Vec<(&'static str, usize, Vec<Line>)>,
+    /// The line "END." was found on, or the last line of the file if there wasn't one.
+    end_line: usize,
 
     num_courses: usize,
     num_rooms: usize,
@@ -16,204 +79,336 @@ pub struct Loader {
 }
 
 impl Loader {
-    pub fn new(path: &str) -> Self {
-        Loader {
+    pub fn new(path: &str) -> Result<Self, DataError> {
+        Loader::with_dialect(path, Dialect::Ectt)
+    }
+
+    pub fn with_dialect(path: &str, dialect: Dialect) -> Result<Self, DataError> {
+        let mut loader = Loader {
             instance: Instance::new(),
-            lines: get_lines_from_file(path),
+            path: path.to_owned(),
+            dialect,
+            header: Vec::new(),
+            sections: Vec::new(),
+            end_line: 0,
             num_courses: 0,
             num_rooms: 0,
             num_curricula: 0,
             num_unavailable_constraints: 0,
             num_room_constraints: 0,
-        }
+        };
+        loader.split_sections(get_lines_from_file(path)?)?;
+        Ok(loader)
     }
 
     pub fn load(mut self) -> TimetableDataResult {
-        self.read_header();
-        self.read_courses();
-        self.read_rooms();
-        self.read_curricula();
-        self.read_timeslot_constraints();
-        self.read_room_constraints();
+        self.read_header()?;
+        // NOTE: Curricula and constraints refer to courses and rooms, so the sections are
+        // read in this order, whatever order they were in the file.
+        self.read_courses()?;
+        self.read_rooms()?;
+        self.read_curricula()?;
+        self.read_timeslot_constraints()?;
+        self.read_room_constraints()?;
 
         Ok(self.instance)
     }
 
-    /// Read the header of the ectt file.
-    fn read_header(&mut self) {
-        self.read_name();
-        self.read_num_courses();
-        self.read_num_rooms();
-        self.read_timeslots();
-        self.read_num_curricula();
-        self.read_min_max_daily_lectures();
-        self.read_num_unavailable_constraints();
-        self.read_num_room_constraints();
-    }
-
-    fn read_name(&mut self) {
-        let next_line = self.next_line();
-        let name = next_line.split_whitespace().nth(1).unwrap();
-        self.instance.set_name(name.to_owned());
-    }
-
-    fn read_num_courses(&mut self) {
-        let next_line = self.next_line();
-        let num_courses: usize = next_line.split_whitespace().nth(1).unwrap().parse().unwrap();
-        self.num_courses = num_courses;
-    }
-
-    fn read_num_rooms(&mut self) {
-        let next_line = self.next_line();
-        let num_rooms: usize = next_line.split_whitespace().nth(1).unwrap().parse().unwrap();
-        self.num_rooms = num_rooms;
-    }
+    /// Sort the meaningful lines of the file into the header and each of the sections.
+    fn split_sections(&mut self,
+                      lines: std::io::Lines<std::io::BufReader<std::fs::File>>)
+                      -> Result<(), DataError> {
+        let mut current: Option<(&'static str, usize, Vec<Line>)> = None;
+        for (line_index, line) in lines.enumerate() {
+            let line_number = line_index + 1;
+            self.end_line = line_number;
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => {
+                    return Err(DataError::Io {
+                        path: self.path.clone(),
+                        error,
+                    })
+                }
+            };
+            // Strip comments, whitespace and any stray carriage returns.
+            let line = match line.find('#') {
+                Some(index) => &line[..index],
+                None => &line[..],
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
 
-    fn read_timeslots(&mut self) {
-        let days = self.read_num_days();
-        let periods = self.read_periods_per_day();
-        self.instance.set_timeslots(days, periods);
-    }
+            if line == "END." {
+                break;
+            }
+            if let Some(section) = section_from_title(line) {
+                let repeated = self.sections
+                    .iter()
+                    .chain(current.iter())
+                    .any(|&(other, _, _)| other == section);
+                if repeated {
+                    return Err(self.error(section,
+                                          line_number,
+                                          "each section to appear only once",
+                                          line));
+                }
+                if let Some(finished) = current.take() {
+                    self.sections.push(finished);
+                }
+                current = Some((section, line_number, Vec::new()));
+                continue;
+            }
 
-    fn read_num_days(&mut self) -> usize {
-        let next_line = self.next_line();
-        next_line.split_whitespace().nth(1).unwrap().parse().unwrap()
+            match current {
+                Some((_, _, ref mut section_lines)) => {
+                    section_lines.push((line_number, line.to_owned()))
+                }
+                None => self.header.push((line_number, line.to_owned())),
+            }
+        }
+        if let Some(finished) = current.take() {
+            self.sections.push(finished);
+        }
+        Ok(())
     }
 
-    fn read_periods_per_day(&mut self) -> usize {
-        let next_line = self.next_line();
-        next_line.split_whitespace().nth(1).unwrap().parse().unwrap()
-    }
+    /// Read the header of the ectt file.
+    fn read_header(&mut self) -> Result<(), DataError> {
+        let extended = self.dialect == Dialect::Ectt;
+        let mut name = None;
+        let mut num_courses = None;
+        let mut num_rooms = None;
+        let mut days = None;
+        let mut periods = None;
+        let mut num_curricula = None;
+        let mut daily_lectures = None;
+        let mut num_unavailable_constraints = None;
+        let mut num_room_constraints = None;
+
+        let header = std::mem::take(&mut self.header);
+        for &(line_number, ref line) in header.iter() {
+            let expected = "<key>: <value>";
+            let mut split = line.splitn(2, ':');
+            let key = split.next().unwrap().trim();
+            let value = self.field(HEADER, line_number, split.next(), expected, line)?
+                .trim();
+            let parse = |expected: &str| {
+                self.parse::<usize>(HEADER, line_number, Some(value), expected, line)
+            };
+
+            match key.to_lowercase().as_ref() {
+                "name" => name = Some(value.to_owned()),
+                "courses" => num_courses = Some(parse("Courses: <number>")?),
+                "rooms" => num_rooms = Some(parse("Rooms: <number>")?),
+                "days" => days = Some(parse("Days: <number>")?),
+                "periods_per_day" => periods = Some(parse("Periods_per_day: <number>")?),
+                "curricula" => num_curricula = Some(parse("Curricula: <number>")?),
+                "min_max_daily_lectures" if extended => {
+                    let expected = "Min_Max_Daily_Lectures: <min> <max>";
+                    let mut split = value.split_whitespace();
+                    let min: usize =
+                        self.parse(HEADER, line_number, split.next(), expected, line)?;
+                    let max: usize =
+                        self.parse(HEADER, line_number, split.next(), expected, line)?;
+                    daily_lectures = Some((min, max));
+                }
+                "unavailabilityconstraints" if extended => {
+                    num_unavailable_constraints =
+                        Some(parse("UnavailabilityConstraints: <number>")?)
+                }
+                "roomconstraints" if extended => {
+                    num_room_constraints = Some(parse("RoomConstraints: <number>")?)
+                }
+                "constraints" if !extended => {
+                    num_unavailable_constraints = Some(parse("Constraints: <number>")?)
+                }
+                _ => {
+                    return Err(self.error(HEADER, line_number, "a known header key", line))
+                }
+            }
+        }
 
-    fn read_num_curricula(&mut self) {
-        let next_line = self.next_line();
-        let num_curricula: usize = next_line.split_whitespace().nth(1).unwrap().parse().unwrap();
-        self.num_curricula = num_curricula;
-    }
+        // Make sure nothing was left out of the header.
+        let header_end = self.sections.first().map_or(self.end_line, |&(_, line, _)| line);
+        let path = self.path.clone();
+        let missing = |key: &str| {
+            DataError::Parse {
+                path: path.clone(),
+                line: header_end,
+                section: HEADER,
+                expected: format!("a \"{}:\" line in the header", key),
+                found: "the end of the header".to_owned(),
+            }
+        };
+        let name = name.ok_or_else(|| missing("Name"))?;
+        self.num_courses = num_courses.ok_or_else(|| missing("Courses"))?;
+        self.num_rooms = num_rooms.ok_or_else(|| missing("Rooms"))?;
+        let days = days.ok_or_else(|| missing("Days"))?;
+        let periods = periods.ok_or_else(|| missing("Periods_per_day"))?;
+        self.num_curricula = num_curricula.ok_or_else(|| missing("Curricula"))?;
+        if extended {
+            let daily_lectures =
+                daily_lectures.ok_or_else(|| missing("Min_Max_Daily_Lectures"))?;
+            self.instance.set_daily_lectures(daily_lectures);
+            self.num_unavailable_constraints = num_unavailable_constraints.ok_or_else(|| {
+                    missing("UnavailabilityConstraints")
+                })?;
+            self.num_room_constraints =
+                num_room_constraints.ok_or_else(|| missing("RoomConstraints"))?;
+        } else {
+            self.num_unavailable_constraints =
+                num_unavailable_constraints.ok_or_else(|| missing("Constraints"))?;
+            self.num_room_constraints = 0;
+        }
 
-    fn read_min_max_daily_lectures(&mut self) {
-        let next_line = self.next_line();
-        let mut split = next_line.split_whitespace();
-        let min: usize = split.nth(1).unwrap().parse().unwrap();
-        let max: usize = split.next().unwrap().parse().unwrap();
-        let min_max = (min, max);
-        self.instance.set_daily_lectures(min_max);
+        self.instance.set_name(name);
+        self.instance.set_timeslots(days, periods);
+        Ok(())
     }
 
-    fn read_num_unavailable_constraints(&mut self) {
-        let next_line = self.next_line();
-        let num_constraints: usize = next_line.split_whitespace().nth(1).unwrap().parse().unwrap();
-        self.num_unavailable_constraints = num_constraints;
-    }
+    /// Take the lines of a section out of the loader, checking there are as many as the header
+    /// said there would be.
+    fn take_section(&mut self, section: &'static str, count: usize) -> Result<Vec<Line>, DataError> {
+        let (start_line, lines) = match self.sections.iter().position(|&(other, _, _)| {
+            other == section
+        }) {
+            Some(index) => {
+                let (_, start_line, lines) = self.sections.remove(index);
+                (start_line, lines)
+            }
+            None => (self.end_line, Vec::new()),
+        };
 
-    fn read_num_room_constraints(&mut self) {
-        let next_line = self.next_line();
-        let num_constraints: usize = next_line.split_whitespace().nth(1).unwrap().parse().unwrap();
-        self.num_room_constraints = num_constraints;
+        if lines.len() != count {
+            return Err(DataError::Parse {
+                path: self.path.clone(),
+                line: start_line,
+                section,
+                expected: format!("{} entries (as declared in the header)", count),
+                found: format!("{} entries", lines.len()),
+            });
+        }
+        Ok(lines)
     }
 
-    fn read_courses(&mut self) {
-        // Skip whitepspace
-        let _ = self.next_line();
-        // Skip "COURSES:"
-        let _ = self.next_line();
-
-        for _ in 0..self.num_courses {
-            self.read_course();
+    fn read_courses(&mut self) -> Result<(), DataError> {
+        let num_courses = self.num_courses;
+        for (line_number, line) in self.take_section(COURSES, num_courses)? {
+            self.read_course(line_number, &line)?;
         }
+        Ok(())
     }
 
-    fn read_course(&mut self) {
-        let line = self.next_line();
+    fn read_course(&mut self, line_number: usize, line: &str) -> Result<(), DataError> {
+        let section = COURSES;
+        let expected = match self.dialect {
+            Dialect::Ectt => {
+                "<course> <teacher> <lectures> <min days> <students> <double lectures>"
+            }
+            Dialect::Ctt => "<course> <teacher> <lectures> <min days> <students>",
+        };
         let mut split = line.split_whitespace();
 
-        let course_id = split.next().unwrap().to_owned();
-        let teacher_id = split.next().unwrap().to_owned();
-        let num_lectures: usize = split.next().unwrap().parse().unwrap();
-        // TODO(zac): Figure out how to use this feature we extracted from the data.
-        let min_days: usize = split.next().unwrap().parse().unwrap();
-        let num_students: usize = split.next().unwrap().parse().unwrap();
-        let double_lectures: usize = split.next().unwrap().parse().unwrap();
+        let course_id = self.field(section, line_number, split.next(), expected, line)?
+            .to_owned();
+        let teacher_id = self.field(section, line_number, split.next(), expected, line)?
+            .to_owned();
+        let num_lectures: usize = self.parse(section, line_number, split.next(), expected, line)?;
+        let min_days: usize = self.parse(section, line_number, split.next(), expected, line)?;
+        let num_students: usize = self.parse(section, line_number, split.next(), expected, line)?;
+        let double_lectures: usize = match self.dialect {
+            Dialect::Ectt => self.parse(section, line_number, split.next(), expected, line)?,
+            Dialect::Ctt => 0,
+        };
 
         let double_lectures = match double_lectures {
             0 => false,
             1 => true,
-            _ => panic!("Encountered {} when expected 0 or 1", double_lectures),
-        };
-
-        // TODO(zac): Figure out how to use this feature we extracted from the data.
-        let num_timeslots = if double_lectures {
-            2
-        } else {
-            1
+            _ => return Err(self.error(section, line_number, "0 or 1 for double lectures", line)),
         };
 
         // Schedule an event for the number of lectures for this course.
-        for _ in 0..num_lectures {
-            let event = Event::new(course_id.clone(), teacher_id.clone(), num_students);
-            self.instance.add_event(event);
-        }
+        self.instance.add_course(&course_id,
+                                 &teacher_id,
+                                 num_lectures,
+                                 min_days,
+                                 num_students,
+                                 double_lectures);
+        Ok(())
     }
 
-    fn read_rooms(&mut self) {
-        // skip whitespace
-        let _ = self.next_line();
-        // skip "ROOMS:"
-        let _ = self.next_line();
-
-        for _ in 0..self.num_rooms {
-            self.read_room();
+    fn read_rooms(&mut self) -> Result<(), DataError> {
+        let num_rooms = self.num_rooms;
+        for (line_number, line) in self.take_section(ROOMS, num_rooms)? {
+            self.read_room(line_number, &line)?;
         }
+        Ok(())
     }
 
-    fn read_room(&mut self) {
-        let line = self.next_line();
+    fn read_room(&mut self, line_number: usize, line: &str) -> Result<(), DataError> {
+        let section = ROOMS;
+        let expected = match self.dialect {
+            Dialect::Ectt => "<room> <capacity> <building>",
+            Dialect::Ctt => "<room> <capacity>",
+        };
         let mut split = line.split_whitespace();
 
-        let room_id = split.next().unwrap().to_owned();
-        let capacity: usize = split.next().unwrap().parse().unwrap();
-        let building_id = split.next().unwrap().to_owned();
+        let room_id = self.field(section, line_number, split.next(), expected, line)?.to_owned();
+        let capacity: usize = self.parse(section, line_number, split.next(), expected, line)?;
+        let building_id = match self.dialect {
+            Dialect::Ectt => {
+                self.field(section, line_number, split.next(), expected, line)?.to_owned()
+            }
+            Dialect::Ctt => String::new(),
+        };
 
         let room = Room::new(room_id, capacity, building_id);
         self.instance.add_room(room);
+        Ok(())
     }
 
-    fn read_curricula(&mut self) {
-        // skip whitespace
-        let _ = self.next_line();
-        // skip "CURRICULA:"
-        let _ = self.next_line();
-
-        for _ in 0..self.num_curricula {
-            self.read_curriculum();
+    fn read_curricula(&mut self) -> Result<(), DataError> {
+        let num_curricula = self.num_curricula;
+        for (line_number, line) in self.take_section(CURRICULA, num_curricula)? {
+            self.read_curriculum(line_number, &line)?;
         }
+        Ok(())
     }
 
-    fn read_curriculum(&mut self) {
-        let line = self.next_line();
+    fn read_curriculum(&mut self, line_number: usize, line: &str) -> Result<(), DataError> {
+        let section = CURRICULA;
+        let expected = "<curriculum> <number of courses> <course>...";
         let mut split = line.split_whitespace();
 
-        let curriculum_id = split.next().unwrap().to_owned();
-        let num_courses: usize = split.next().unwrap().parse().unwrap();
+        let curriculum_id = self.field(section, line_number, split.next(), expected, line)?
+            .to_owned();
+        let num_courses: usize = self.parse(section, line_number, split.next(), expected, line)?;
+        let mut course_ids = Vec::with_capacity(num_courses);
         for _ in 0..num_courses {
-            let course_id = split.next().unwrap();
-            let events = self.instance.mut_events_with_course_id(course_id);
-            for mut event in events {
+            let course_id = self.course_id(section, line_number, split.next(), expected, line)?;
+            for event in self.instance.mut_events_with_course_id(course_id) {
                 event.add_curriculum_id(curriculum_id.clone());
             }
+            course_ids.push(course_id.to_owned());
+        }
+        if split.next().is_some() {
+            return Err(self.error(section, line_number, expected, line));
         }
 
+        self.instance.add_curriculum(Curriculum::new(curriculum_id, course_ids));
+        Ok(())
     }
 
-    fn read_timeslot_constraints(&mut self) {
-        // skip whitespace
-        let _ = self.next_line();
-        // skip "UNAVAILABILITY_CONSTRAINTS:"
-        let _ = self.next_line();
+    fn read_timeslot_constraints(&mut self) -> Result<(), DataError> {
+        let num_constraints = self.num_unavailable_constraints;
+        let lines = self.take_section(UNAVAILABILITY_CONSTRAINTS, num_constraints)?;
 
-        let mut course_timeslot_tuples = Vec::with_capacity(self.num_unavailable_constraints);
-        for _ in 0..self.num_unavailable_constraints {
-            course_timeslot_tuples.push(self.read_timeslot_constraint());
+        let mut course_timeslot_tuples = Vec::with_capacity(lines.len());
+        for (line_number, line) in lines {
+            course_timeslot_tuples.push(self.read_timeslot_constraint(line_number, &line)?);
         }
         // Make sure course tuples are all grouped together.
         course_timeslot_tuples.sort_by(|a, b| a.0.cmp(&b.0));
@@ -234,43 +429,50 @@ impl Loader {
                 banned_timeslots.push(tuple.1);
             }
 
-            for mut event in self.instance.mut_events_with_course_id(&current_id).iter_mut() {
+            for event in self.instance.mut_events_with_course_id(&current_id).iter_mut() {
                 event.add_constraint(TimeSlotConstraint::new());
                 event.set_banned_timeslots(banned_timeslots.clone());
             }
         }
-
-
+        Ok(())
     }
 
-    fn read_timeslot_constraint(&mut self) -> (String, TimeSlot) {
-        let line = self.next_line();
+    fn read_timeslot_constraint(&mut self,
+                                line_number: usize,
+                                line: &str)
+                                -> Result<(String, TimeSlot), DataError> {
+        let section = UNAVAILABILITY_CONSTRAINTS;
+        let expected = "<course> <day> <period>";
         let mut split = line.split_whitespace();
 
-        let course_id = split.next().unwrap();
-        let day: usize = split.next().unwrap().parse().unwrap();
-        let period: usize = split.next().unwrap().parse().unwrap();
+        let course_id = self.course_id(section, line_number, split.next(), expected, line)?;
+        let day: usize = self.parse(section, line_number, split.next(), expected, line)?;
+        let period: usize = self.parse(section, line_number, split.next(), expected, line)?;
+        if day >= self.instance.days() || period >= self.instance.periods_per_day() {
+            return Err(self.error(section,
+                                  line_number,
+                                  "a day and period within the header's bounds",
+                                  line));
+        }
 
         let timeslot = TimeSlot::new(day, period);
-        (course_id.to_owned(), timeslot)
+        Ok((course_id.to_owned(), timeslot))
     }
 
-    fn read_room_constraints(&mut self) {
-        // skip whitespace
-        let _ = self.next_line();
-        // skip "ROOM_CONSTRAINTS:"
-        let _ = self.next_line();
+    fn read_room_constraints(&mut self) -> Result<(), DataError> {
+        let num_constraints = self.num_room_constraints;
+        let lines = self.take_section(ROOM_CONSTRAINTS, num_constraints)?;
 
-        let mut course_room_tuples = Vec::with_capacity(self.num_room_constraints);
-        for _ in 0..self.num_room_constraints {
-            course_room_tuples.push(self.read_room_constraint());
+        let mut course_room_tuples = Vec::with_capacity(lines.len());
+        for (line_number, line) in lines {
+            course_room_tuples.push(self.read_room_constraint(line_number, &line)?);
         }
         // Make sure the course tuples are all grouped together.
         course_room_tuples.sort_by(|a, b| a.0.cmp(&b.0));
         while course_room_tuples.len() != 0 {
             let current_id = course_room_tuples[0].0.clone();
 
-            let mut valid_rooms = Vec::new();
+            let mut unsuitable_rooms = Vec::new();
             // While there are tuples of courses and rooms
             // with the current id...
             while course_room_tuples.get(0)
@@ -280,43 +482,180 @@ impl Loader {
                 .is_some() {
                 // Remove the tuple from this list, and
                 let tuple = course_room_tuples.remove(0);
-                let room_index =
-                    self.instance.rooms().iter().position(|ref room| room.id() == tuple.1).unwrap();
-                // Put it into the valid_rooms list for the current it.
-                valid_rooms.push(room_index);
+                // Put it into the unsuitable_rooms list for the current it.
+                unsuitable_rooms.push(tuple.1);
             }
 
-            for mut event in self.instance.mut_events_with_course_id(&current_id).iter_mut() {
-                event.add_constraint(RoomConstraint::new());
-                event.set_valid_rooms(valid_rooms.clone());
+            let num_rooms = self.instance.num_rooms();
+            for event in self.instance.mut_events_with_course_id(&current_id).iter_mut() {
+                for &room_index in unsuitable_rooms.iter() {
+                    event.ban_room(room_index, num_rooms);
+                }
             }
         }
+        Ok(())
     }
 
-    /// Reads a tuple that is (CourseID, RoomID)
-    fn read_room_constraint(&mut self) -> (String, String) {
-        let line = self.next_line();
+    /// Reads a tuple that is (CourseID, room index)
+    fn read_room_constraint(&mut self,
+                            line_number: usize,
+                            line: &str)
+                            -> Result<(String, usize), DataError> {
+        let section = ROOM_CONSTRAINTS;
+        let expected = "<course> <room>";
         let mut split = line.split_whitespace();
 
-        let course_id = split.next().unwrap().to_owned();
-        let room_id: String = split.next().unwrap().to_owned();
-        (course_id, room_id)
+        let course_id = self.course_id(section, line_number, split.next(), expected, line)?
+            .to_owned();
+        let room_id = self.field(section, line_number, split.next(), expected, line)?;
+        let room_index = match self.instance.rooms().iter().position(|room| room.id() == room_id) {
+            Some(room_index) => room_index,
+            None => {
+                return Err(self.error(section,
+                                      line_number,
+                                      "a room id from the ROOMS section",
+                                      line))
+            }
+        };
+        Ok((course_id, room_index))
+    }
+
+    /// Build an error for the specified line.
+    fn error(&self, section: &'static str, line_number: usize, expected: &str, line: &str) -> DataError {
+        DataError::Parse {
+            path: self.path.clone(),
+            line: line_number,
+            section,
+            expected: expected.to_owned(),
+            found: line.to_owned(),
+        }
+    }
+
+    /// Make sure a field of the line was actually there, and is the id of a course from the
+    /// COURSES section.
+    fn course_id<'a>(&self,
+                     section: &'static str,
+                     line_number: usize,
+                     field: Option<&'a str>,
+                     expected: &str,
+                     line: &str)
+                     -> Result<&'a str, DataError> {
+        let course_id = self.field(section, line_number, field, expected, line)?;
+        if self.instance.events().iter().any(|event| event.course_id() == course_id) {
+            Ok(course_id)
+        } else {
+            Err(self.error(section, line_number, "a course id from the COURSES section", line))
+        }
+    }
+
+    /// Make sure a field of the line was actually there.
+    fn field<'a>(&self,
+                 section: &'static str,
+                 line_number: usize,
+                 field: Option<&'a str>,
+                 expected: &str,
+                 line: &str)
+                 -> Result<&'a str, DataError> {
+        field.ok_or_else(|| self.error(section, line_number, expected, line))
+    }
+
+    /// Parse a field of the line into a value.
+    fn parse<T: FromStr>(&self,
+                         section: &'static str,
+                         line_number: usize,
+                         field: Option<&str>,
+                         expected: &str,
+                         line: &str)
+                         -> Result<T, DataError> {
+        let field = self.field(section, line_number, field, expected, line)?;
+        field.parse().map_err(|_| self.error(section, line_number, expected, line))
     }
+}
 
-    /// Get the next line from the lines interator.
-    fn next_line(&mut self) -> String {
-        self.lines.next().unwrap().unwrap()
+/// Works out which section a line is the title of, if any.
+fn section_from_title(line: &str) -> Option<&'static str> {
+    match line {
+        "COURSES:" => Some(COURSES),
+        "ROOMS:" => Some(ROOMS),
+        "CURRICULA:" => Some(CURRICULA),
+        "UNAVAILABILITY_CONSTRAINTS:" => Some(UNAVAILABILITY_CONSTRAINTS),
+        "ROOM_CONSTRAINTS:" => Some(ROOM_CONSTRAINTS),
+        _ => None,
     }
 }
 
 // TODO(zac): put in a file io utils folder.
-fn get_lines_from_file(path: &str) -> std::io::Lines<std::io::BufReader<std::fs::File>> {
+fn get_lines_from_file(path: &str)
+                       -> Result<std::io::Lines<std::io::BufReader<std::fs::File>>, DataError> {
     use std::fs::File;
     use std::io::{BufRead, BufReader};
     let file = match File::open(path) {
         Ok(file) => BufReader::new(file),
-        Err(msg) => panic!(msg),
+        Err(error) => {
+            return Err(DataError::Io {
+                path: path.to_owned(),
+                error,
+            })
+        }
     };
 
-    file.lines()
+    Ok(file.lines())
+}
+
+#[cfg(test)]
+mod tests {
+    use std;
+    use std::io::Write;
+
+    use data::{self, DataError, InstanceFormat, TimetableDataResult};
+    use super::*;
+
+    /// Load the test instance with one line of its contents replaced.
+    fn load_with(name: &str, line: &str, replacement: &str) -> TimetableDataResult {
+        let contents = data::TEST_INSTANCE.replacen(line, replacement, 1);
+        assert!(contents != data::TEST_INSTANCE, "The test instance has no line {:?}.", line);
+        let path = std::env::temp_dir()
+            .join(format!("time_table_{}_{}.ectt", std::process::id(), name));
+        let path = path.to_str().expect("Temporary path is not valid unicode.");
+        std::fs::File::create(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .expect("Could not write the test file.");
+        let result = EcttFormat.load(path);
+        let _ = std::fs::remove_file(path);
+        result
+    }
+
+    fn assert_unknown_course(result: TimetableDataResult, section: &'static str) {
+        match result {
+            Err(DataError::Parse { section: found, ref expected, .. }) => {
+                assert_eq!(found, section);
+                assert_eq!(expected, "a course id from the COURSES section");
+            }
+            other => panic!("Expected an unknown course error, found {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn unknown_courses_in_constraints_are_errors() {
+        assert_unknown_course(load_with("unknown_unavailable_course", "C7 2 1", "C9 2 1"),
+                              UNAVAILABILITY_CONSTRAINTS);
+        assert_unknown_course(load_with("unknown_room_course", "C6 R2", "C9 R2"),
+                              ROOM_CONSTRAINTS);
+    }
+
+    #[test]
+    fn room_constraints_ban_the_listed_rooms() {
+        let instance = data::test_instance("ectt_room_constraints");
+        let valid_rooms = |course_id: &str| {
+            let event = instance.events()
+                .iter()
+                .find(|event| event.course_id() == course_id)
+                .unwrap();
+            event.valid_rooms().map(|rooms| rooms.to_owned())
+        };
+        // C3 can't use R0 and C6 can't use R2, while C0 has no room constraints.
+        assert_eq!(valid_rooms("C3"), Some(vec![1, 2]));
+        assert_eq!(valid_rooms("C6"), Some(vec![0, 1]));
+        assert_eq!(valid_rooms("C0"), None);
+    }
 }
@@ -0,0 +1,104 @@
+use std;
+use std::fmt;
+
+/// An error encountered while loading timetable data from a file.
+#[derive(Debug)]
+pub enum DataError {
+    /// The file could not be opened or read.
+    Io {
+        path: String,
+        error: std::io::Error,
+    },
+    /// The file ended before everything that was expected had been read.
+    UnexpectedEof {
+        path: String,
+        section: &'static str,
+        expected: String,
+    },
+    /// A line of the file did not contain what was expected.
+    Parse {
+        path: String,
+        line: usize,
+        /// The part of the file being read, such as "COURSES".
+        section: &'static str,
+        expected: String,
+        found: String,
+    },
+    /// The file asks for something that can't be represented, such as an unknown kind of
+    /// constraint.
+    Unsupported {
+        path: String,
+        line: usize,
+        section: &'static str,
+        found: String,
+    },
+    /// A cell of a CSV file did not contain what was expected.
+    Csv {
+        path: String,
+        row: usize,
+        /// The heading of the column the cell is in.
+        column: String,
+        expected: String,
+        found: String,
+    },
+    /// A value in a JSON file was missing, or was not what was expected.
+    Json {
+        path: String,
+        /// Where the value is in the document, such as "events[3].teacher".
+        location: String,
+        expected: String,
+    },
+    /// The format of the file could not be recognised.
+    UnknownFormat {
+        path: String,
+        /// The names of the formats that were tried.
+        known: Vec<String>,
+    },
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DataError::Io { ref path, ref error } => write!(f, "{}: {}", path, error),
+            DataError::UnexpectedEof { ref path, section, ref expected } => {
+                write!(f,
+                       "{}: in {}: expected {}, but the file ended",
+                       path,
+                       section,
+                       expected)
+            }
+            DataError::Parse { ref path, line, section, ref expected, ref found } => {
+                write!(f,
+                       "{}:{}: in {}: expected {}, found \"{}\"",
+                       path,
+                       line,
+                       section,
+                       expected,
+                       found)
+            }
+            DataError::Unsupported { ref path, line, section, ref found } => {
+                write!(f, "{}:{}: in {}: {} is not supported", path, line, section, found)
+            }
+            DataError::Csv { ref path, row, ref column, ref expected, ref found } => {
+                write!(f,
+                       "{}:{}: in column \"{}\": expected {}, found \"{}\"",
+                       path,
+                       row,
+                       column,
+                       expected,
+                       found)
+            }
+            DataError::Json { ref path, ref location, ref expected } => {
+                write!(f, "{}: at {}: expected {}", path, location, expected)
+            }
+            DataError::UnknownFormat { ref path, ref known } => {
+                write!(f,
+                       "{}: not in a recognised format (known formats: {})",
+                       path,
+                       known.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
@@ -1,16 +1,23 @@
 use data::constraints::*;
 use data::timeslot::*;
+use util;
 
 pub struct Event {
     course_id: String,
     teacher: String,
     students: usize,
-    curriculum_id: Option<String>, // TODO(zac): Can a course be part of multiple curriculums?
+    /// The minimum number of days the lectures of this event's course should be spread over.
+    min_working_days: usize,
+    /// The curricula this event's course belongs to.
+    curriculum_ids: Vec<String>,
+    /// The event this one should be held back to back with, as a double lecture.
+    double_lecture_partner: Option<usize>,
 
     // TODO(zac): differentiate between soft and hard constraints somehow.
     constraints: Vec<Box<Constraint>>,
     banned_timeslots: Vec<TimeSlot>,
-    valid_rooms: Vec<usize>,
+    /// None if every room is valid for this event.
+    valid_rooms: Option<Vec<usize>>,
 }
 
 impl Event {
@@ -19,15 +26,19 @@ impl Event {
             course_id: id,
             teacher: teacher,
             students: students,
-            curriculum_id: None,
+            min_working_days: 0,
+            curriculum_ids: Vec::new(),
+            double_lecture_partner: None,
             constraints: Vec::new(),
             banned_timeslots: Vec::new(),
-            valid_rooms: Vec::new(),
+            valid_rooms: None,
         }
     }
 
     pub fn add_curriculum_id(&mut self, id: String) {
-        self.curriculum_id = Some(id);
+        if !self.curriculum_ids.contains(&id) {
+            self.curriculum_ids.push(id);
+        }
     }
 
     pub fn add_constraint(&mut self, constraint: Box<Constraint>) {
@@ -46,8 +57,30 @@ impl Event {
         self.students
     }
 
-    pub fn curriculum_id(&self) -> Option<&String> {
-        self.curriculum_id.as_ref()
+    pub fn set_min_working_days(&mut self, min_working_days: usize) {
+        self.min_working_days = min_working_days;
+    }
+
+    pub fn min_working_days(&self) -> usize {
+        self.min_working_days
+    }
+
+    pub fn set_double_lecture_partner(&mut self, event_index: usize) {
+        self.double_lecture_partner = Some(event_index);
+    }
+
+    /// The index of the event this one forms a double lecture with, if any.
+    pub fn double_lecture_partner(&self) -> Option<usize> {
+        self.double_lecture_partner
+    }
+
+    pub fn curriculum_ids(&self) -> &[String] {
+        &self.curriculum_ids
+    }
+
+    /// Returns true if this event has a curriculum in common with the other event.
+    pub fn shares_curriculum_with(&self, other: &Event) -> bool {
+        self.curriculum_ids.iter().any(|id| other.curriculum_ids.contains(id))
     }
 
     pub fn constraints(&self) -> &[Box<Constraint>] {
@@ -66,15 +99,35 @@ impl Event {
         self.banned_timeslots.push(timeslot);
     }
 
+    /// Ban the event from being allocated to the specified timeslot, making sure there is a
+    /// TimeSlotConstraint to enforce it.
+    pub fn ban_timeslot(&mut self, timeslot: TimeSlot) {
+        if self.banned_timeslots.is_empty() {
+            self.add_constraint(TimeSlotConstraint::new());
+        }
+        if !self.banned_timeslots.contains(&timeslot) {
+            self.add_banned_timeslot(timeslot);
+        }
+    }
+
     pub fn set_valid_rooms(&mut self, valid_rooms: Vec<usize>) {
-        self.valid_rooms = valid_rooms;
+        self.valid_rooms = Some(valid_rooms);
     }
 
+    /// Returns None if every room is valid for this event.
     pub fn valid_rooms(&self) -> Option<&[usize]> {
-        if self.valid_rooms.len() == 0 {
-            None
-        } else {
-            Some(&self.valid_rooms)
+        self.valid_rooms.as_deref()
+    }
+
+    /// Ban the event from being allocated to the specified room. If every room was previously
+    /// valid, the full list of rooms is built first (and a RoomConstraint added to enforce it).
+    pub fn ban_room(&mut self, room_index: usize, num_rooms: usize) {
+        if self.valid_rooms.is_none() {
+            self.set_valid_rooms(util::vec_from_range(0..num_rooms));
+            self.add_constraint(RoomConstraint::new());
+        }
+        if let Some(ref mut valid_rooms) = self.valid_rooms {
+            valid_rooms.retain(|&index| index != room_index);
         }
     }
 }
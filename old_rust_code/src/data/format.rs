@@ -0,0 +1,118 @@
+use std;
+use std::io::Read;
+
+use data::{ectt, ctt, tim, xml, json, csv, DataError, TimetableDataResult};
+
+/// The number of bytes at the start of a file that are used to recognise its format.
+const SNIFF_LENGTH: u64 = 4096;
+
+/// A file format that instances can be loaded from.
+pub trait InstanceFormat {
+    /// A short name for the format, such as "ectt".
+    fn name(&self) -> &str;
+
+    /// The file extensions (without the '.') used by files of this format.
+    fn extensions(&self) -> &[&str];
+
+    /// Returns true if the start of a file looks like this format.
+    fn sniff(&self, head: &str) -> bool;
+
+    fn load(&self, path: &str) -> TimetableDataResult;
+}
+
+/// The formats that instances can be loaded from. A file's format is chosen by its extension, or
+/// failing that, by which format recognises the start of the file.
+pub struct FormatRegistry {
+    formats: Vec<Box<dyn InstanceFormat>>,
+}
+
+impl FormatRegistry {
+    /// Creates a registry without any formats.
+    pub fn new() -> Self {
+        FormatRegistry { formats: Vec::new() }
+    }
+
+    /// Creates a registry with every format this crate can load.
+    pub fn with_default_formats() -> Self {
+        let mut registry = FormatRegistry::new();
+        registry.register(Box::new(ectt::EcttFormat));
+        registry.register(Box::new(ctt::CttFormat));
+        registry.register(Box::new(tim::TimFormat));
+        registry.register(Box::new(xml::XmlFormat));
+        registry.register(Box::new(json::JsonFormat));
+        registry.register(Box::new(csv::CsvFormat));
+        registry
+    }
+
+    /// Add a format to the registry. Formats registered first take priority when more than one
+    /// of them matches a file.
+    pub fn register(&mut self, format: Box<dyn InstanceFormat>) {
+        self.formats.push(format);
+    }
+
+    pub fn formats(&self) -> &[Box<dyn InstanceFormat>] {
+        &self.formats
+    }
+
+    /// Find the format of a file by its extension.
+    pub fn find_by_extension(&self, path: &str) -> Option<&dyn InstanceFormat> {
+        let extension = match std::path::Path::new(path).extension() {
+            Some(extension) => extension.to_string_lossy().to_lowercase(),
+            None => return None,
+        };
+        self.formats
+            .iter()
+            .find(|format| format.extensions().iter().any(|&other| other == extension))
+            .map(|format| &**format)
+    }
+
+    /// Find the format of a file by reading the start of it.
+    pub fn find_by_content(&self, path: &str) -> Result<Option<&dyn InstanceFormat>, DataError> {
+        let head = read_head(path)?;
+        Ok(self.formats
+            .iter()
+            .find(|format| format.sniff(&head))
+            .map(|format| &**format))
+    }
+
+    /// Find the format of a file, by its extension if that is known, otherwise by its content.
+    pub fn detect(&self, path: &str) -> Result<&dyn InstanceFormat, DataError> {
+        if let Some(format) = self.find_by_extension(path) {
+            return Ok(format);
+        }
+        match self.find_by_content(path)? {
+            Some(format) => Ok(format),
+            None => {
+                Err(DataError::UnknownFormat {
+                    path: path.to_owned(),
+                    known: self.formats.iter().map(|format| format.name().to_owned()).collect(),
+                })
+            }
+        }
+    }
+
+    /// Load an instance from a file in any of the registered formats.
+    pub fn load(&self, path: &str) -> TimetableDataResult {
+        self.detect(path)?.load(path)
+    }
+}
+
+impl Default for FormatRegistry {
+    fn default() -> Self {
+        FormatRegistry::new()
+    }
+}
+
+/// Read the start of a file, for recognising its format.
+fn read_head(path: &str) -> Result<String, DataError> {
+    let io_error = |error| {
+        DataError::Io {
+            path: path.to_owned(),
+            error,
+        }
+    };
+    let file = std::fs::File::open(path).map_err(&io_error)?;
+    let mut head = Vec::new();
+    file.take(SNIFF_LENGTH).read_to_end(&mut head).map_err(&io_error)?;
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
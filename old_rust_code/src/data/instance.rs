@@ -1,6 +1,6 @@
 use data::*;
 
-pub type TimetableDataResult = Result<Instance, String>;
+pub type TimetableDataResult = Result<Instance, DataError>;
 
 /// A storage of data loaded from some source. This displays the specific problem, as loaded from
 /// some source.
@@ -12,8 +12,13 @@ pub struct Instance {
     periods_per_day: usize,
     rooms: Vec<Room>,
     events: Vec<Event>,
+    curricula: Vec<Curriculum>,
     /// Any constraints that are not specific to individual events.
     constraints: Vec<Box<Constraint>>,
+    /// Constraints that are checked over groups of events, rather than single allocations.
+    group_constraints: Vec<Box<dyn GroupConstraint>>,
+    /// The formulation the group constraints come from.
+    formulation: Formulation,
 }
 
 impl Instance {
@@ -23,6 +28,9 @@ impl Instance {
         constraints.push(CurriculumConstraint::new());
         constraints.push(TeacherConstraint::new());
 
+        let formulation = Formulation::default();
+        let group_constraints = formulation.group_constraints();
+
         Instance {
             name: None,
             daily_lectures: (0, 24),
@@ -31,7 +39,10 @@ impl Instance {
             periods_per_day: 0,
             rooms: Vec::new(),
             events: Vec::new(),
+            curricula: Vec::new(),
             constraints: constraints,
+            group_constraints,
+            formulation,
         }
     }
 
@@ -56,6 +67,13 @@ impl Instance {
         self.daily_lectures = min_max;
     }
 
+    /// Judge timetables by the constraints of the formulation, replacing the group constraints
+    /// of the previous one. Candidates must be created after the formulation is set.
+    pub fn set_formulation(&mut self, formulation: Formulation) {
+        self.formulation = formulation;
+        self.group_constraints = formulation.group_constraints();
+    }
+
     pub fn events(&self) -> &[Event] {
         &self.events
     }
@@ -64,10 +82,38 @@ impl Instance {
         self.events.push(event);
     }
 
+    /// Add an event for each lecture of a course. The lectures of a course with double lectures
+    /// are paired up, leaving the last one on its own if there is an odd number of them.
+    pub fn add_course(&mut self,
+                      course_id: &str,
+                      teacher_id: &str,
+                      num_lectures: usize,
+                      min_days: usize,
+                      num_students: usize,
+                      double_lectures: bool) {
+        let first_event_index = self.num_events();
+        for lecture in 0..num_lectures {
+            let mut event = Event::new(course_id.to_owned(), teacher_id.to_owned(), num_students);
+            event.set_min_working_days(min_days);
+            if double_lectures {
+                if lecture % 2 == 1 {
+                    event.set_double_lecture_partner(first_event_index + lecture - 1);
+                } else if lecture + 1 < num_lectures {
+                    event.set_double_lecture_partner(first_event_index + lecture + 1);
+                }
+            }
+            self.add_event(event);
+        }
+    }
+
     pub fn add_room(&mut self, room: Room) {
         self.rooms.push(room);
     }
 
+    pub fn add_curriculum(&mut self, curriculum: Curriculum) {
+        self.curricula.push(curriculum);
+    }
+
     // Accessors:
 
     /// Retreives the name of this instance, or an empty &str instead.
@@ -94,6 +140,21 @@ impl Instance {
         self.periods_per_day * day + period
     }
 
+    /// The minimum and maximum number of lectures a curriculum should have on a day it is taught.
+    pub fn daily_lectures(&self) -> (usize, usize) {
+        self.daily_lectures
+    }
+
+    /// Returns true if the second timeslot directly follows the first, on the same day.
+    pub fn are_consecutive(&self, first_index: usize, second_index: usize) -> bool {
+        match (self.timeslot(first_index), self.timeslot(second_index)) {
+            (Some(first), Some(second)) => {
+                first.day == second.day && first.period + 1 == second.period
+            }
+            _ => false,
+        }
+    }
+
     pub fn days(&self) -> usize {
         self.days
     }
@@ -119,6 +180,16 @@ impl Instance {
         &self.constraints
     }
 
+    /// The formulation the group constraints come from.
+    pub fn formulation(&self) -> Formulation {
+        self.formulation
+    }
+
+    /// Borrow the list of constraints that are checked over groups of events.
+    pub fn group_constraints(&self) -> &[Box<dyn GroupConstraint>] {
+        &self.group_constraints
+    }
+
     pub fn mut_events_with_course_id(&mut self, course_id: &str) -> Vec<&mut Event> {
         self.events.iter_mut().filter(|ref event| event.course_id() == course_id).collect()
     }
@@ -131,6 +202,14 @@ impl Instance {
         &self.rooms
     }
 
+    pub fn curriculum(&self, curriculum_index: usize) -> Option<&Curriculum> {
+        self.curricula.get(curriculum_index)
+    }
+
+    pub fn curricula(&self) -> &[Curriculum] {
+        &self.curricula
+    }
+
     pub fn num_timeslots(&self) -> usize {
         self.timeslots.len()
     }
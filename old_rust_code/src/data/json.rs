@@ -0,0 +1,581 @@
+//! JSON versions of instances and solutions, for tools that can't read the text formats.
+//!
+//! An instance is written as:
+//!
+//! ```text
+//! {
+//!   "name": "comp01",                        (or null)
+//!   "days": 5,
+//!   "periods_per_day": 6,
+//!   "daily_lectures": { "min": 2, "max": 5 },
+//!   "formulation": "ud2",                    (or "ud4", see data::Formulation)
+//!   "timeslots": [ { "day": 0, "period": 0 }, ... ],
+//!   "rooms": [ { "id": "B", "capacity": 200, "building": "1" }, ... ],
+//!   "curricula": [ { "id": "q000", "courses": ["c0001", "c0002"] }, ... ],
+//!   "events": [
+//!     {
+//!       "course": "c0001",
+//!       "teacher": "t000",
+//!       "students": 130,
+//!       "min_working_days": 5,
+//!       "curricula": ["q000"],
+//!       "double_lecture_partner": 1,           (an index into "events", or null)
+//!       "banned_timeslots": [ { "day": 4, "period": 0 }, ... ],
+//!       "valid_rooms": ["B", ...]              (room ids, or null if every room is valid)
+//!     },
+//!     ...
+//!   ]
+//! }
+//! ```
+//!
+//! The timeslots follow from the days and periods, so they are only written for convenience, and
+//! are ignored when reading.
+//!
+//! A solution is written as:
+//!
+//! ```text
+//! {
+//!   "instance": "comp01",                    (or null)
+//!   "cost": { "hard": 0, "soft": 12 },
+//!   "unallocated_events": 0,
+//!   "allocations": [
+//!     { "event": 0, "course": "c0001", "room": "B", "day": 0, "period": 3,
+//!       "cost": { "hard": 0, "soft": 2 } },
+//!     ...
+//!   ]
+//! }
+//! ```
+//!
+//! When a solution is read, only the event, course, room, day and period of each allocation are
+//! used. The costs are worked out again from the instance.
+
+use std;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use rustc_serialize::json::{self, Json};
+
+use candidate::{CandidateSolution, Cost};
+use data::{Instance, Event, Curriculum, TimetableDataResult, Room, TimeSlot, RoomConstraint,
+           DataError, InstanceFormat, Formulation};
+
+/// Instances written by `save_instance`.
+pub struct JsonFormat;
+
+impl InstanceFormat for JsonFormat {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+
+    fn sniff(&self, head: &str) -> bool {
+        head.trim_start().starts_with('{') && head.contains("\"days\"")
+    }
+
+    fn load(&self, path: &str) -> TimetableDataResult {
+        load_instance(path)
+    }
+}
+
+/// Write the instance to the file at path, as JSON.
+pub fn save_instance(path: &str, instance: &Instance) -> std::io::Result<()> {
+    save(path, &instance_to_json(instance))
+}
+
+/// Load an instance from a JSON file at path.
+pub fn load_instance(path: &str) -> TimetableDataResult {
+    let document = read(path)?;
+    instance_from_json(path, &document)
+}
+
+/// Write the candidate to the file at path, as JSON.
+pub fn save_solution(path: &str,
+                     candidate: &CandidateSolution,
+                     instance: &Instance)
+                     -> std::io::Result<()> {
+    save(path, &solution_to_json(candidate, instance))
+}
+
+/// Load a JSON solution file at path onto the instance.
+pub fn load_solution(path: &str, instance: &Instance) -> Result<CandidateSolution, DataError> {
+    let document = read(path)?;
+    solution_from_json(path, &document, instance)
+}
+
+pub fn instance_to_json(instance: &Instance) -> Json {
+    let rooms = instance.rooms()
+        .iter()
+        .map(|room| {
+            let mut object = BTreeMap::new();
+            object.insert("id".to_owned(), Json::String(room.id().to_owned()));
+            object.insert("capacity".to_owned(), number(room.capacity()));
+            object.insert("building".to_owned(), Json::String(room.building().to_owned()));
+            Json::Object(object)
+        })
+        .collect();
+
+    let curricula = instance.curricula()
+        .iter()
+        .map(|curriculum| {
+            let mut object = BTreeMap::new();
+            object.insert("id".to_owned(), Json::String(curriculum.id().to_owned()));
+            object.insert("courses".to_owned(), strings(curriculum.course_ids()));
+            Json::Object(object)
+        })
+        .collect();
+
+    let events = instance.events()
+        .iter()
+        .map(|event| {
+            let mut object = BTreeMap::new();
+            object.insert("course".to_owned(), Json::String(event.course_id().to_owned()));
+            object.insert("teacher".to_owned(), Json::String(event.teacher().to_owned()));
+            object.insert("students".to_owned(), number(event.num_students()));
+            object.insert("min_working_days".to_owned(), number(event.min_working_days()));
+            object.insert("curricula".to_owned(), strings(event.curriculum_ids()));
+            object.insert("double_lecture_partner".to_owned(),
+                          event.double_lecture_partner().map_or(Json::Null, number));
+            object.insert("banned_timeslots".to_owned(),
+                          Json::Array(event.banned_timeslots().iter().map(timeslot).collect()));
+            let valid_rooms = event.valid_rooms().map_or(Json::Null, |rooms| {
+                Json::Array(rooms.iter()
+                    .map(|&room_index| Json::String(instance.rooms()[room_index].id().to_owned()))
+                    .collect())
+            });
+            object.insert("valid_rooms".to_owned(), valid_rooms);
+            Json::Object(object)
+        })
+        .collect();
+
+    let (min, max) = instance.daily_lectures();
+    let mut daily_lectures = BTreeMap::new();
+    daily_lectures.insert("min".to_owned(), number(min));
+    daily_lectures.insert("max".to_owned(), number(max));
+
+    let mut object = BTreeMap::new();
+    object.insert("name".to_owned(),
+                  instance.name().map_or(Json::Null, |name| Json::String(name.to_owned())));
+    object.insert("days".to_owned(), number(instance.days()));
+    object.insert("periods_per_day".to_owned(), number(instance.periods_per_day()));
+    object.insert("daily_lectures".to_owned(), Json::Object(daily_lectures));
+    object.insert("formulation".to_owned(),
+                  Json::String(instance.formulation().name().to_owned()));
+    object.insert("timeslots".to_owned(),
+                  Json::Array(instance.timeslots().iter().map(timeslot).collect()));
+    object.insert("rooms".to_owned(), Json::Array(rooms));
+    object.insert("curricula".to_owned(), Json::Array(curricula));
+    object.insert("events".to_owned(), Json::Array(events));
+    Json::Object(object)
+}
+
+pub fn instance_from_json(path: &str, document: &Json) -> TimetableDataResult {
+    let reader = Reader { path };
+    let mut instance = Instance::new();
+
+    if let Some(name) = reader.optional(document, "name") {
+        instance.set_name(reader.as_string(name, "name")?.to_owned());
+    }
+    let days = reader.number(document, "", "days")?;
+    let periods = reader.number(document, "", "periods_per_day")?;
+    instance.set_timeslots(days, periods);
+    if let Some(daily_lectures) = reader.optional(document, "daily_lectures") {
+        let min = reader.number(daily_lectures, "daily_lectures", "min")?;
+        let max = reader.number(daily_lectures, "daily_lectures", "max")?;
+        instance.set_daily_lectures((min, max));
+    }
+    if let Some(formulation) = reader.optional(document, "formulation") {
+        match Formulation::from_name(reader.as_string(formulation, "formulation")?) {
+            Some(formulation) => instance.set_formulation(formulation),
+            None => return Err(reader.error("formulation", "\"ud2\" or \"ud4\"")),
+        }
+    }
+
+    for (index, room) in reader.array(document, "", "rooms")?.iter().enumerate() {
+        let location = format!("rooms[{}]", index);
+        let id = reader.string(room, &location, "id")?.to_owned();
+        let capacity = reader.number(room, &location, "capacity")?;
+        let building = match reader.optional(room, "building") {
+            Some(building) => reader.as_string(building, &at(&location, "building"))?.to_owned(),
+            None => String::new(),
+        };
+        instance.add_room(Room::new(id, capacity, building));
+    }
+
+    for (index, curriculum) in reader.array(document, "", "curricula")?.iter().enumerate() {
+        let location = format!("curricula[{}]", index);
+        let id = reader.string(curriculum, &location, "id")?.to_owned();
+        let courses = reader.strings(curriculum, &location, "courses")?;
+        instance.add_curriculum(Curriculum::new(id, courses));
+    }
+
+    let events = reader.array(document, "", "events")?;
+    for (index, value) in events.iter().enumerate() {
+        let location = format!("events[{}]", index);
+        let course = reader.string(value, &location, "course")?.to_owned();
+        let teacher = reader.string(value, &location, "teacher")?.to_owned();
+        let students = reader.number(value, &location, "students")?;
+        let mut event = Event::new(course, teacher, students);
+
+        if reader.optional(value, "min_working_days").is_some() {
+            event.set_min_working_days(reader.number(value, &location, "min_working_days")?);
+        }
+        if reader.optional(value, "curricula").is_some() {
+            for curriculum_id in reader.strings(value, &location, "curricula")? {
+                event.add_curriculum_id(curriculum_id);
+            }
+        }
+        if reader.optional(value, "double_lecture_partner").is_some() {
+            let partner = reader.number(value, &location, "double_lecture_partner")?;
+            if partner >= events.len() || partner == index {
+                return Err(reader.error(&at(&location, "double_lecture_partner"),
+                                        "the index of another event"));
+            }
+            event.set_double_lecture_partner(partner);
+        }
+        if reader.optional(value, "banned_timeslots").is_some() {
+            let banned_timeslots = reader.array(value, &location, "banned_timeslots")?;
+            for (index, banned) in banned_timeslots.iter().enumerate() {
+                let location = format!("{}.banned_timeslots[{}]", location, index);
+                let day = reader.number(banned, &location, "day")?;
+                let period = reader.number(banned, &location, "period")?;
+                if day >= instance.days() || period >= instance.periods_per_day() {
+                    return Err(reader.error(&location, "a day and period within the instance"));
+                }
+                event.ban_timeslot(TimeSlot::new(day, period));
+            }
+        }
+        if reader.optional(value, "valid_rooms").is_some() {
+            let mut room_indices = Vec::new();
+            for room_id in reader.strings(value, &location, "valid_rooms")? {
+                match instance.rooms().iter().position(|room| room.id() == room_id) {
+                    Some(room_index) => room_indices.push(room_index),
+                    None => {
+                        return Err(reader.error(&at(&location, "valid_rooms"),
+                                                "room ids from \"rooms\""))
+                    }
+                }
+            }
+            event.add_constraint(RoomConstraint::new());
+            event.set_valid_rooms(room_indices);
+        }
+        instance.add_event(event);
+    }
+
+    Ok(instance)
+}
+
+pub fn solution_to_json(candidate: &CandidateSolution, instance: &Instance) -> Json {
+    let mut allocations = candidate.allocations();
+    allocations.sort_by_key(|allocation| allocation.event_index());
+    let allocations = allocations.iter()
+        .map(|allocation| {
+            let event = instance.event(allocation.event_index())
+                .expect("Invalid event index found in an allocation.");
+            let room = instance.room(allocation.room_index())
+                .expect("Invalid room index found in an allocation.");
+            let timeslot = instance.timeslot(allocation.timeslot_index())
+                .expect("Invalid timeslot index found in an allocation.");
+
+            let mut object = BTreeMap::new();
+            object.insert("event".to_owned(), number(allocation.event_index()));
+            object.insert("course".to_owned(), Json::String(event.course_id().to_owned()));
+            object.insert("room".to_owned(), Json::String(room.id().to_owned()));
+            object.insert("day".to_owned(), number(timeslot.day));
+            object.insert("period".to_owned(), number(timeslot.period));
+            object.insert("cost".to_owned(), cost(allocation.cost()));
+            Json::Object(object)
+        })
+        .collect();
+
+    let mut object = BTreeMap::new();
+    object.insert("instance".to_owned(),
+                  instance.name().map_or(Json::Null, |name| Json::String(name.to_owned())));
+    object.insert("cost".to_owned(), cost(candidate.cost()));
+    object.insert("unallocated_events".to_owned(),
+                  number(candidate.num_unallocated_events()));
+    object.insert("allocations".to_owned(), Json::Array(allocations));
+    Json::Object(object)
+}
+
+pub fn solution_from_json(path: &str,
+                          document: &Json,
+                          instance: &Instance)
+                          -> Result<CandidateSolution, DataError> {
+    let reader = Reader { path };
+    let mut candidate = CandidateSolution::new(instance);
+
+    for (index, allocation) in reader.array(document, "", "allocations")?.iter().enumerate() {
+        let location = format!("allocations[{}]", index);
+
+        let event_index = reader.number(allocation, &location, "event")?;
+        let event = match instance.event(event_index) {
+            Some(event) => event,
+            None => {
+                return Err(reader.error(&at(&location, "event"),
+                                        "an event index from the instance"))
+            }
+        };
+        if reader.string(allocation, &location, "course")? != event.course_id() {
+            return Err(reader.error(&at(&location, "course"), "the course of the event"));
+        }
+        let room_id = reader.string(allocation, &location, "room")?;
+        let room_index = match instance.rooms().iter().position(|room| room.id() == room_id) {
+            Some(room_index) => room_index,
+            None => return Err(reader.error(&at(&location, "room"), "a room id from the instance")),
+        };
+        let day = reader.number(allocation, &location, "day")?;
+        let period = reader.number(allocation, &location, "period")?;
+        if day >= instance.days() || period >= instance.periods_per_day() {
+            return Err(reader.error(&location, "a day and period within the instance"));
+        }
+        let timeslot_index = instance.to_timeslot_index(day, period);
+
+        if candidate.allocation_index_of(event_index).is_some() {
+            return Err(reader.error(&at(&location, "event"),
+                                    "an event that is not already allocated"));
+        }
+        if candidate.get_allocation(timeslot_index, room_index).is_some() {
+            return Err(reader.error(&location, "a room that is not already in use at that time"));
+        }
+        candidate.allocate_event(timeslot_index, room_index, Some(event_index), instance);
+    }
+
+    Ok(candidate)
+}
+
+fn save(path: &str, document: &Json) -> std::io::Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    writeln!(file, "{}", document.pretty())
+}
+
+fn read(path: &str) -> Result<Json, DataError> {
+    let io_error = |error| {
+        DataError::Io {
+            path: path.to_owned(),
+            error,
+        }
+    };
+    let mut contents = String::new();
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(&io_error)?;
+
+    Json::from_str(&contents).map_err(|error| {
+        match error {
+            json::ParserError::SyntaxError(code, line, column) => {
+                DataError::Json {
+                    path: path.to_owned(),
+                    location: format!("line {}, column {}", line, column),
+                    expected: format!("valid JSON ({})", json::error_str(code)),
+                }
+            }
+            json::ParserError::IoError(error) => io_error(error),
+        }
+    })
+}
+
+fn number(value: usize) -> Json {
+    Json::U64(value as u64)
+}
+
+fn strings(values: &[String]) -> Json {
+    Json::Array(values.iter().map(|value| Json::String(value.clone())).collect())
+}
+
+fn timeslot(timeslot: &TimeSlot) -> Json {
+    let mut object = BTreeMap::new();
+    object.insert("day".to_owned(), number(timeslot.day));
+    object.insert("period".to_owned(), number(timeslot.period));
+    Json::Object(object)
+}
+
+fn cost(cost: Cost) -> Json {
+    let mut object = BTreeMap::new();
+    object.insert("hard".to_owned(), number(cost.hard));
+    object.insert("soft".to_owned(), number(cost.soft));
+    Json::Object(object)
+}
+
+/// The location of a field of the object at location.
+fn at(location: &str, key: &str) -> String {
+    if location.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", location, key)
+    }
+}
+
+/// Reads values out of a JSON document, reporting where in the document anything was wrong.
+/// Each value is found by the location of its object, and its key in that object.
+struct Reader<'a> {
+    path: &'a str,
+}
+
+impl<'a> Reader<'a> {
+    /// Find a field of an object that has to be there.
+    fn field<'j>(&self,
+                 object: &'j Json,
+                 location: &str,
+                 key: &str)
+                 -> Result<&'j Json, DataError> {
+        let object_location = if location.is_empty() { "the top level" } else { location };
+        match *object {
+            Json::Object(ref object) => {
+                object.get(key).ok_or_else(|| {
+                    self.error(object_location, &format!("a \"{}\" field", key))
+                })
+            }
+            _ => Err(self.error(object_location, "an object")),
+        }
+    }
+
+    /// Find a field of an object that can be left out, or be null.
+    fn optional<'j>(&self, object: &'j Json, key: &str) -> Option<&'j Json> {
+        object.find(key).and_then(|value| if value.is_null() { None } else { Some(value) })
+    }
+
+    fn number(&self, object: &Json, location: &str, key: &str) -> Result<usize, DataError> {
+        let value = self.field(object, location, key)?;
+        value.as_u64().map(|value| value as usize).ok_or_else(|| {
+            self.error(&at(location, key), "a whole number")
+        })
+    }
+
+    fn string<'j>(&self,
+                  object: &'j Json,
+                  location: &str,
+                  key: &str)
+                  -> Result<&'j str, DataError> {
+        let value = self.field(object, location, key)?;
+        self.as_string(value, &at(location, key))
+    }
+
+    fn array<'j>(&self,
+                 object: &'j Json,
+                 location: &str,
+                 key: &str)
+                 -> Result<&'j [Json], DataError> {
+        let value = self.field(object, location, key)?;
+        value.as_array().map(|array| &array[..]).ok_or_else(|| {
+            self.error(&at(location, key), "an array")
+        })
+    }
+
+    fn strings(&self, object: &Json, location: &str, key: &str) -> Result<Vec<String>, DataError> {
+        let mut strings = Vec::new();
+        for (index, value) in self.array(object, location, key)?.iter().enumerate() {
+            let location = format!("{}[{}]", at(location, key), index);
+            strings.push(self.as_string(value, &location)?.to_owned());
+        }
+        Ok(strings)
+    }
+
+    fn as_string<'j>(&self, value: &'j Json, location: &str) -> Result<&'j str, DataError> {
+        value.as_string().ok_or_else(|| self.error(location, "a string"))
+    }
+
+    fn error(&self, location: &str, expected: &str) -> DataError {
+        DataError::Json {
+            path: self.path.to_owned(),
+            location: location.to_owned(),
+            expected: expected.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, StdRng};
+
+    use data::{self, Instance};
+    use candidate::CandidateSolution;
+    use super::*;
+
+    /// A path in the temporary directory for a test to write to.
+    fn temporary_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("time_table_{}_{}.json", std::process::id(), name))
+            .to_str()
+            .expect("Temporary path is not valid unicode.")
+            .to_owned()
+    }
+
+    fn assert_same_instance(instance: &Instance, loaded: &Instance) {
+        assert_eq!(instance.name(), loaded.name());
+        assert_eq!(instance.days(), loaded.days());
+        assert_eq!(instance.periods_per_day(), loaded.periods_per_day());
+        assert_eq!(instance.daily_lectures(), loaded.daily_lectures());
+        assert_eq!(instance.formulation(), loaded.formulation());
+        assert!(instance.timeslots() == loaded.timeslots());
+        assert!(instance.rooms() == loaded.rooms());
+
+        assert_eq!(instance.curricula().len(), loaded.curricula().len());
+        for (curriculum, other) in instance.curricula().iter().zip(loaded.curricula()) {
+            assert_eq!(curriculum.id(), other.id());
+            assert_eq!(curriculum.course_ids(), other.course_ids());
+        }
+
+        assert_eq!(instance.num_events(), loaded.num_events());
+        for (event, other) in instance.events().iter().zip(loaded.events()) {
+            assert_eq!(event.course_id(), other.course_id());
+            assert_eq!(event.teacher(), other.teacher());
+            assert_eq!(event.num_students(), other.num_students());
+            assert_eq!(event.min_working_days(), other.min_working_days());
+            assert_eq!(event.curriculum_ids(), other.curriculum_ids());
+            assert_eq!(event.double_lecture_partner(), other.double_lecture_partner());
+            assert!(event.banned_timeslots() == other.banned_timeslots());
+            assert_eq!(event.valid_rooms(), other.valid_rooms());
+            let names = |event: &Event| -> Vec<String> {
+                event.constraints().iter().map(|constraint| constraint.name().to_owned()).collect()
+            };
+            assert_eq!(names(event), names(other));
+        }
+    }
+
+    #[test]
+    fn instance_round_trip() {
+        let mut instance = data::test_instance("json_instance");
+        instance.set_formulation(Formulation::Ud4);
+        let path = temporary_path("instance");
+        save_instance(&path, &instance).unwrap();
+        let loaded = load_instance(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_same_instance(&instance, &loaded.unwrap());
+    }
+
+    #[test]
+    fn solution_round_trip() {
+        let instance = data::test_instance("json_solution");
+        let mut rng: StdRng = SeedableRng::from_seed(&[7][..]);
+        let mut candidate = CandidateSolution::new(&instance);
+        // Leave a few events unallocated, so that they are round-tripped too.
+        for event_index in 2..instance.num_events() {
+            let timeslot_index = rng.gen_range(0, instance.num_timeslots());
+            let room_index = rng.gen_range(0, instance.num_rooms());
+            if candidate.get_allocation(timeslot_index, room_index).is_none() {
+                candidate.allocate_event(timeslot_index, room_index, Some(event_index), &instance);
+            }
+        }
+
+        let path = temporary_path("solution");
+        save_solution(&path, &candidate, &instance).unwrap();
+        let loaded = load_solution(&path, &instance);
+        let _ = std::fs::remove_file(&path);
+        let loaded = loaded.unwrap();
+
+        for timeslot_index in 0..instance.num_timeslots() {
+            for room_index in 0..instance.num_rooms() {
+                let event_index = |candidate: &CandidateSolution| {
+                    candidate.get_allocation(timeslot_index, room_index)
+                        .map(|allocation| allocation.event_index())
+                };
+                assert_eq!(event_index(&candidate), event_index(&loaded));
+            }
+        }
+        assert_eq!(candidate.num_unallocated_events(), loaded.num_unallocated_events());
+        assert_eq!(candidate.cost(), loaded.cost());
+    }
+}
@@ -1,5 +1,18 @@
+use std;
+
+use candidate::CandidateSolution;
+
 mod ectt;
+mod ctt;
+mod tim;
+mod xml;
+mod json;
+mod csv;
+mod sol;
+pub mod format;
+pub mod error;
 pub mod event;
+pub mod curriculum;
 pub mod instance;
 pub mod room;
 pub mod timeslot;
@@ -7,12 +20,128 @@ pub mod constraints;
 
 // export the following modules out of this module.
 pub use data::event::*;
+pub use data::curriculum::*;
 pub use data::instance::*;
 pub use data::room::*;
 pub use data::timeslot::*;
 pub use data::constraints::*;
+pub use data::error::*;
+pub use data::format::*;
 
+/// Load an instance from a file in any of the default formats, recognised by its extension or
+/// its content.
 pub fn load(path: &str) -> TimetableDataResult {
-    let loader = ectt::Loader::new(path);
-    loader.load()
+    FormatRegistry::with_default_formats().load(path)
+}
+
+/// Save the candidate to a solution file, in the ITC-2007 (track 3) solution format.
+pub fn save_solution(path: &str,
+                     candidate: &CandidateSolution,
+                     instance: &Instance)
+                     -> std::io::Result<()> {
+    sol::save(path, candidate, instance)
+}
+
+/// Load a solution file (in the ITC-2007 track 3 solution format) onto the instance.
+pub fn load_solution(path: &str, instance: &Instance) -> Result<CandidateSolution, DataError> {
+    sol::load(path, instance)
+}
+
+/// Save the instance to a JSON file. See the data::json module for the layout of the file.
+pub fn save_instance_json(path: &str, instance: &Instance) -> std::io::Result<()> {
+    json::save_instance(path, instance)
+}
+
+/// Save the candidate to a JSON file, along with the cost of each of its allocations.
+pub fn save_solution_json(path: &str,
+                          candidate: &CandidateSolution,
+                          instance: &Instance)
+                          -> std::io::Result<()> {
+    json::save_solution(path, candidate, instance)
+}
+
+/// Load a JSON solution file onto the instance.
+pub fn load_solution_json(path: &str, instance: &Instance) -> Result<CandidateSolution, DataError> {
+    json::load_solution(path, instance)
+}
+
+/// Save the instance as a directory of CSV files. See the data::csv module for the files.
+pub fn save_instance_csv(directory: &str, instance: &Instance) -> std::io::Result<()> {
+    csv::save_instance(directory, instance)
+}
+
+/// Load an instance from a directory of CSV files (or from any file in the directory).
+pub fn load_instance_csv(path: &str) -> TimetableDataResult {
+    csv::load_instance(path)
+}
+
+/// Save the allocations of the candidate to a CSV file, along with their costs.
+pub fn save_solution_csv(path: &str,
+                         candidate: &CandidateSolution,
+                         instance: &Instance)
+                         -> std::io::Result<()> {
+    csv::save_solution(path, candidate, instance)
+}
+
+/// A small ECTT instance for tests. Its courses share teachers and curricula, some have double
+/// lectures, and some have unavailable timeslots and rooms.
+#[cfg(test)]
+pub const TEST_INSTANCE: &str = "Name: Test
+Courses: 8
+Rooms: 3
+Days: 5
+Periods_per_day: 4
+Curricula: 3
+Min_Max_Daily_Lectures: 1 3
+UnavailabilityConstraints: 4
+RoomConstraints: 2
+
+COURSES:
+C0 T0 3 2 30 0
+C1 T1 2 2 45 1
+C2 T0 4 3 20 1
+C3 T2 2 1 60 0
+C4 T3 3 3 25 0
+C5 T1 2 2 35 1
+C6 T4 1 1 15 0
+C7 T2 3 2 40 0
+
+ROOMS:
+R0 30 0
+R1 50 0
+R2 70 1
+
+CURRICULA:
+Q0 3 C0 C1 C2
+Q1 3 C2 C3 C4
+Q2 3 C4 C5 C7
+
+UNAVAILABILITY_CONSTRAINTS:
+C0 0 0
+C2 1 3
+C4 4 2
+C7 2 1
+
+ROOM_CONSTRAINTS:
+C3 R0
+C6 R2
+
+END.
+";
+
+/// Loads TEST_INSTANCE from a file named after the test.
+#[cfg(test)]
+pub fn test_instance(name: &str) -> Instance {
+    use std::io::Write;
+
+    // Each test has its own file, as tests run in parallel.
+    let path = std::env::temp_dir()
+        .join(format!("time_table_{}_{}.ectt", std::process::id(), name));
+    let path = path.to_str().expect("Temporary path is not valid unicode.");
+    std::fs::File::create(path)
+        .and_then(|mut file| file.write_all(TEST_INSTANCE.as_bytes()))
+        .expect("Could not write the test instance.");
+    let instance = load(path).expect("Could not load the test instance.");
+    let _ = std::fs::remove_file(path);
+    instance
 }
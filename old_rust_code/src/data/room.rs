@@ -5,8 +5,7 @@ pub type RoomID = String;
 pub struct Room {
     id: RoomID,
     capacity: usize,
-    // NOTE(zac): I'm not sure I need this field, but I'll keep it here for now, just in case.
-    _building: BuildingID,
+    building: BuildingID,
 }
 
 impl Room {
@@ -14,7 +13,7 @@ impl Room {
         Room {
             id: id,
             capacity: capacity,
-            _building: building,
+            building,
         }
     }
 
@@ -25,4 +24,8 @@ impl Room {
     pub fn capacity(&self) -> usize {
         self.capacity
     }
+
+    pub fn building(&self) -> &str {
+        &self.building
+    }
 }
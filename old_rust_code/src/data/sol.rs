@@ -0,0 +1,244 @@
+use std;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use candidate::CandidateSolution;
+use data::{Instance, DataError};
+
+/// Write the candidate to the file at path, in the ITC-2007 (track 3) solution format.
+pub fn save(path: &str,
+            candidate: &CandidateSolution,
+            instance: &Instance)
+            -> std::io::Result<()> {
+    let file = std::fs::File::create(path)?;
+    let mut writer = std::io::BufWriter::new(file);
+    write(&mut writer, candidate, instance)
+}
+
+/// Write the candidate in the ITC-2007 (track 3) solution format. Each allocated event is
+/// written on its own line as:
+///
+/// ```text
+/// <course id> <room id> <day> <period>
+/// ```
+pub fn write<W: Write>(writer: &mut W,
+                       candidate: &CandidateSolution,
+                       instance: &Instance)
+                       -> std::io::Result<()> {
+    // Gather up the allocations, so that the lectures of each course are written together.
+    let mut allocations = Vec::new();
+    for timeslot_index in 0..candidate.num_timeslots() {
+        for room_index in 0..candidate.num_rooms() {
+            if let Some(allocation) = candidate.get_allocation(timeslot_index, room_index) {
+                allocations.push(allocation);
+            }
+        }
+    }
+    allocations.sort_by_key(|allocation| allocation.event_index());
+
+    for allocation in allocations {
+        let event = instance.event(allocation.event_index())
+            .expect("Invalid event index found in an allocation.");
+        let room = instance.room(allocation.room_index())
+            .expect("Invalid room index found in an allocation.");
+        let timeslot = instance.timeslot(allocation.timeslot_index())
+            .expect("Invalid timeslot index found in an allocation.");
+        writeln!(writer,
+                 "{} {} {} {}",
+                 event.course_id(),
+                 room.id(),
+                 timeslot.day,
+                 timeslot.period)?;
+    }
+    Ok(())
+}
+
+/// Load a solution in the ITC-2007 (track 3) solution format from the file at path, allocating
+/// each of its lectures to the events of the instance. Lectures of the same course in the same
+/// period are loaded, as they are a conflict for the constraints to count, but a line that
+/// repeats another, or a room that is used twice in the same period, is an error.
+pub fn load(path: &str, instance: &Instance) -> Result<CandidateSolution, DataError> {
+    let io_error = |error| {
+        DataError::Io {
+            path: path.to_owned(),
+            error,
+        }
+    };
+    let file = std::fs::File::open(path).map_err(&io_error)?;
+    let reader = std::io::BufReader::new(file);
+
+    let mut candidate = CandidateSolution::new(instance);
+    // The events of each course that are yet to be allocated, in the order they appear in the
+    // instance.
+    let mut unallocated: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (event_index, event) in instance.events().iter().enumerate().rev() {
+        unallocated.entry(event.course_id()).or_default().push(event_index);
+    }
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line.map_err(&io_error)?;
+        let error = |expected: &str| {
+            DataError::Parse {
+                path: path.to_owned(),
+                line: line_index + 1,
+                section: "solution",
+                expected: expected.to_owned(),
+                found: line.trim().to_owned(),
+            }
+        };
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+        if fields.len() != 4 {
+            return Err(error("<course> <room> <day> <period>"));
+        }
+
+        let course_id = fields[0];
+        let course_events = match unallocated.get_mut(course_id) {
+            Some(course_events) => course_events,
+            None => return Err(error("a course id from the instance")),
+        };
+        let room_index = match instance.rooms().iter().position(|room| room.id() == fields[1]) {
+            Some(room_index) => room_index,
+            None => return Err(error("a room id from the instance")),
+        };
+        let day: usize = match fields[2].parse() {
+            Ok(day) if day < instance.days() => day,
+            _ => return Err(error("a day within the instance")),
+        };
+        let period: usize = match fields[3].parse() {
+            Ok(period) if period < instance.periods_per_day() => period,
+            _ => return Err(error("a period within the instance")),
+        };
+        let timeslot_index = instance.to_timeslot_index(day, period);
+
+        if let Some(allocation) = candidate.get_allocation(timeslot_index, room_index) {
+            let other = instance.event(allocation.event_index())
+                .expect("Invalid event index found in an allocation.");
+            if other.course_id() == course_id {
+                return Err(error("a lecture that is not a duplicate"));
+            }
+            return Err(error("a room that is not already in use at that time"));
+        }
+        let event_index = match course_events.pop() {
+            Some(event_index) => event_index,
+            None => return Err(error("no more lectures of the course than the instance has")),
+        };
+
+        candidate.allocate_event(timeslot_index, room_index, Some(event_index), instance);
+    }
+
+    Ok(candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use std;
+    use std::io::Write;
+
+    use rand::{Rng, SeedableRng, StdRng};
+
+    use candidate::CandidateSolution;
+    use data::{self, DataError, Instance};
+    use super::*;
+
+    /// A path in the temporary directory for a test to write to.
+    fn temporary_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("time_table_{}_{}.sol", std::process::id(), name))
+            .to_str()
+            .expect("Temporary path is not valid unicode.")
+            .to_owned()
+    }
+
+    /// Load a solution with the given contents.
+    fn load_contents(name: &str,
+                     contents: &str,
+                     instance: &Instance)
+                     -> Result<CandidateSolution, DataError> {
+        let path = temporary_path(name);
+        std::fs::File::create(&path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .expect("Could not write the test file.");
+        let result = load(&path, instance);
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    fn assert_parse_error(result: Result<CandidateSolution, DataError>,
+                          line: usize,
+                          expected: &str) {
+        match result {
+            Err(DataError::Parse { line: found, expected: ref found_expected, .. }) => {
+                assert_eq!(found, line);
+                assert_eq!(found_expected, expected);
+            }
+            other => panic!("Expected a parse error, found {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn solution_round_trip() {
+        let instance = data::test_instance("sol_round_trip");
+        let mut rng: StdRng = SeedableRng::from_seed(&[11][..]);
+        let mut candidate = CandidateSolution::new(&instance);
+        // Allocate every event, as the lectures of a course are loaded into its first events.
+        for event_index in 0..instance.num_events() {
+            loop {
+                let timeslot_index = rng.gen_range(0, instance.num_timeslots());
+                let room_index = rng.gen_range(0, instance.num_rooms());
+                if candidate.get_allocation(timeslot_index, room_index).is_none() {
+                    candidate.allocate_event(timeslot_index,
+                                             room_index,
+                                             Some(event_index),
+                                             &instance);
+                    break;
+                }
+            }
+        }
+
+        let path = temporary_path("round_trip");
+        save(&path, &candidate, &instance).unwrap();
+        let loaded = load(&path, &instance);
+        let _ = std::fs::remove_file(&path);
+        let loaded = loaded.unwrap();
+
+        for timeslot_index in 0..instance.num_timeslots() {
+            for room_index in 0..instance.num_rooms() {
+                let event_index = |candidate: &CandidateSolution| {
+                    candidate.get_allocation(timeslot_index, room_index)
+                        .map(|allocation| allocation.event_index())
+                };
+                assert_eq!(event_index(&candidate), event_index(&loaded));
+            }
+        }
+        assert_eq!(candidate.num_unallocated_events(), loaded.num_unallocated_events());
+        assert_eq!(candidate.cost(), loaded.cost());
+    }
+
+    #[test]
+    fn unknown_courses_are_errors() {
+        let instance = data::test_instance("sol_unknown_course");
+        let result = load_contents("unknown_course", "C0 R0 0 1\nC9 R1 0 1\n", &instance);
+        assert_parse_error(result, 2, "a course id from the instance");
+    }
+
+    #[test]
+    fn too_many_lectures_are_errors() {
+        let instance = data::test_instance("sol_too_many_lectures");
+        // C6 has a single lecture.
+        let result = load_contents("too_many_lectures", "C6 R0 0 0\nC6 R0 1 0\n", &instance);
+        assert_parse_error(result, 2, "no more lectures of the course than the instance has");
+    }
+
+    #[test]
+    fn double_booked_rooms_are_errors() {
+        let instance = data::test_instance("sol_double_booked");
+        let result = load_contents("double_booked", "C1 R1 2 3\nC2 R1 2 3\n", &instance);
+        assert_parse_error(result, 2, "a room that is not already in use at that time");
+        let result = load_contents("duplicate", "C1 R1 2 3\nC1 R1 2 3\n", &instance);
+        assert_parse_error(result, 2, "a lecture that is not a duplicate");
+    }
+}
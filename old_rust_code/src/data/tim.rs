@@ -0,0 +1,271 @@
+use std;
+use std::io::Read;
+use std::str::FromStr;
+
+use data::{Instance, Event, Curriculum, TimetableDataResult, Room, TimeSlot, DataError,
+           InstanceFormat};
+
+/// The post enrolment problem always has 5 days of 9 periods.
+const DAYS: usize = 5;
+const PERIODS_PER_DAY: usize = 9;
+
+// The parts of the file, as named in errors.
+const HEADER: &str = "header";
+const ROOM_SIZES: &str = "room sizes";
+const ATTENDANCE: &str = "student attendance";
+const ROOM_FEATURES: &str = "room features";
+const EVENT_FEATURES: &str = "event features";
+const EVENT_AVAILABILITY: &str = "event availability";
+const PRECEDENCE: &str = "event precedence";
+
+/// A value in the file, along with the line it was found on.
+type Token = (usize, String);
+
+/// The ITC-2007 post enrolment course timetabling format (track 2).
+pub struct TimFormat;
+
+impl InstanceFormat for TimFormat {
+    fn name(&self) -> &str {
+        "tim"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tim"]
+    }
+
+    fn sniff(&self, head: &str) -> bool {
+        // The file starts with a line of four numbers: events, rooms, features and students.
+        let first_line = head.lines().map(|line| line.trim()).find(|line| !line.is_empty());
+        first_line.is_some_and(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            fields.len() == 4 && fields.iter().all(|field| field.parse::<usize>().is_ok())
+        })
+    }
+
+    fn load(&self, path: &str) -> TimetableDataResult {
+        Loader::new(path)?.load()
+    }
+}
+
+/// Loads an instance from a tim file.
+///
+/// Each event becomes a course with a single lecture, taught by a teacher of its own. The events
+/// each student attends are gathered into a curriculum for that student, so that events sharing
+/// a student are not held at the same time. An event can only be held in the rooms that are big
+/// enough for it and have every feature it needs. Files from the 2002 competition, which have no
+/// availability or precedence sections, are also accepted.
+pub struct Loader {
+    instance: Instance,
+
+    path: String,
+    /// Every value in the file, in order.
+    tokens: Vec<Token>,
+    /// The index of the next token to be read.
+    position: usize,
+}
+
+impl Loader {
+    pub fn new(path: &str) -> Result<Self, DataError> {
+        let mut contents = String::new();
+        std::fs::File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|error| {
+                DataError::Io {
+                    path: path.to_owned(),
+                    error,
+                }
+            })?;
+
+        let mut tokens = Vec::new();
+        for (line_index, line) in contents.lines().enumerate() {
+            for token in line.split_whitespace() {
+                tokens.push((line_index + 1, token.to_owned()));
+            }
+        }
+
+        Ok(Loader {
+            instance: Instance::new(),
+            path: path.to_owned(),
+            tokens,
+            position: 0,
+        })
+    }
+
+    pub fn load(mut self) -> TimetableDataResult {
+        let expected = "<events> <rooms> <features> <students>";
+        let num_events: usize = self.next(HEADER, expected)?;
+        let num_rooms: usize = self.next(HEADER, expected)?;
+        let num_features: usize = self.next(HEADER, expected)?;
+        let num_students: usize = self.next(HEADER, expected)?;
+
+        let name = std::path::Path::new(&self.path)
+            .file_stem()
+            .map_or(self.path.clone(), |stem| stem.to_string_lossy().into_owned());
+        self.instance.set_name(name);
+        self.instance.set_timeslots(DAYS, PERIODS_PER_DAY);
+
+        for room_index in 0..num_rooms {
+            let capacity: usize = self.next(ROOM_SIZES, "the size of a room")?;
+            self.instance.add_room(Room::new(format!("r{}", room_index), capacity, String::new()));
+        }
+
+        // Which events each student attends.
+        let mut attendance = Vec::with_capacity(num_students);
+        for _ in 0..num_students {
+            let mut events = Vec::new();
+            for event_index in 0..num_events {
+                if self.next_flag(ATTENDANCE)? {
+                    events.push(event_index);
+                }
+            }
+            attendance.push(events);
+        }
+
+        let room_features = self.read_matrix(ROOM_FEATURES, num_rooms, num_features)?;
+        let event_features = self.read_matrix(EVENT_FEATURES, num_events, num_features)?;
+
+        for (event_index, needs) in event_features.iter().enumerate() {
+            let id = format!("e{}", event_index);
+            let students = attendance.iter().filter(|events| events.contains(&event_index)).count();
+            let mut event = Event::new(id.clone(), id, students);
+
+            // Ban the rooms that are too small, or missing a feature the event needs.
+            let rooms = self.instance.rooms().iter().zip(room_features.iter());
+            for (room_index, (room, offers)) in rooms.enumerate() {
+                let too_small = room.capacity() < students;
+                let missing_feature =
+                    needs.iter().zip(offers).any(|(&need, &offer)| need && !offer);
+                if too_small || missing_feature {
+                    event.ban_room(room_index, num_rooms);
+                }
+            }
+            self.instance.add_event(event);
+        }
+
+        for (student, events) in attendance.iter().enumerate() {
+            if events.is_empty() {
+                continue;
+            }
+            let curriculum_id = format!("s{}", student);
+            let mut course_ids = Vec::with_capacity(events.len());
+            for &event_index in events.iter() {
+                let event = self.instance.event_mut(event_index).unwrap();
+                event.add_curriculum_id(curriculum_id.clone());
+                course_ids.push(event.course_id().to_owned());
+            }
+            self.instance.add_curriculum(Curriculum::new(curriculum_id, course_ids));
+        }
+
+        // The 2002 format ends here.
+        if self.position < self.tokens.len() {
+            self.read_availability(num_events)?;
+            self.read_precedence(num_events)?;
+        }
+
+        if let Some(&(line_number, ref token)) = self.tokens.get(self.position) {
+            return Err(self.error(PRECEDENCE, line_number, "the end of the file", token));
+        }
+        Ok(self.instance)
+    }
+
+    /// Read which timeslots each event can be held in.
+    fn read_availability(&mut self, num_events: usize) -> Result<(), DataError> {
+        for event_index in 0..num_events {
+            for day in 0..DAYS {
+                for period in 0..PERIODS_PER_DAY {
+                    if !self.next_flag(EVENT_AVAILABILITY)? {
+                        self.instance
+                            .event_mut(event_index)
+                            .unwrap()
+                            .ban_timeslot(TimeSlot::new(day, period));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the order events have to be held in.
+    fn read_precedence(&mut self, num_events: usize) -> Result<(), DataError> {
+        // NOTE: Events can't be ordered yet, so an instance that orders any two of them is
+        // rejected rather than loaded without the ordering.
+        let expected = "1, 0 or -1 for the order of two events";
+        for _ in 0..num_events * num_events {
+            let order: isize = self.next(PRECEDENCE, expected)?;
+            let (line_number, ref token) = self.tokens[self.position - 1];
+            if !(-1..=1).contains(&order) {
+                return Err(self.error(PRECEDENCE, line_number, expected, token));
+            }
+            if order != 0 {
+                return Err(DataError::Unsupported {
+                    path: self.path.clone(),
+                    line: line_number,
+                    section: PRECEDENCE,
+                    found: "an order between two events".to_owned(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a matrix of 0s and 1s, row by row.
+    fn read_matrix(&mut self,
+                   section: &'static str,
+                   rows: usize,
+                   columns: usize)
+                   -> Result<Vec<Vec<bool>>, DataError> {
+        let mut matrix = Vec::with_capacity(rows);
+        for _ in 0..rows {
+            let mut row = Vec::with_capacity(columns);
+            for _ in 0..columns {
+                row.push(self.next_flag(section)?);
+            }
+            matrix.push(row);
+        }
+        Ok(matrix)
+    }
+
+    /// Read the next value, which should be a 0 or a 1.
+    fn next_flag(&mut self, section: &'static str) -> Result<bool, DataError> {
+        let expected = "0 or 1";
+        match self.next(section, expected)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => {
+                let (line_number, ref token) = self.tokens[self.position - 1];
+                Err(self.error(section, line_number, expected, token))
+            }
+        }
+    }
+
+    /// Read and parse the next value in the file.
+    fn next<T: FromStr>(&mut self, section: &'static str, expected: &str) -> Result<T, DataError> {
+        let (line_number, ref token) = match self.tokens.get(self.position) {
+            Some(token) => token.clone(),
+            None => {
+                return Err(DataError::UnexpectedEof {
+                    path: self.path.clone(),
+                    section,
+                    expected: expected.to_owned(),
+                })
+            }
+        };
+        self.position += 1;
+        token.parse().map_err(|_| self.error(section, line_number, expected, token))
+    }
+
+    fn error(&self,
+             section: &'static str,
+             line_number: usize,
+             expected: &str,
+             found: &str)
+             -> DataError {
+        DataError::Parse {
+            path: self.path.clone(),
+            line: line_number,
+            section,
+            expected: expected.to_owned(),
+            found: found.to_owned(),
+        }
+    }
+}
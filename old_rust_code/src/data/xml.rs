@@ -0,0 +1,1137 @@
+use std;
+use std::collections::HashMap;
+use std::io::Read;
+use std::str::FromStr;
+
+use data::{Instance, Curriculum, TimetableDataResult, Room, TimeSlot, DataError, InstanceFormat};
+
+// The parts of the file, as named in errors.
+const HEADER: &str = "header";
+const COURSES: &str = "COURSES";
+const ROOMS: &str = "ROOMS";
+const CURRICULA: &str = "CURRICULA";
+const CONSTRAINTS: &str = "constraints";
+const UNAVAILABILITY_CONSTRAINTS: &str = "UNAVAILABILITY_CONSTRAINTS";
+const ROOM_CONSTRAINTS: &str = "ROOM_CONSTRAINTS";
+const MARKUP: &str = "XML markup";
+const TIMES: &str = "times";
+const RESOURCES: &str = "resources";
+const EVENTS: &str = "events";
+
+/// The XML version of the curriculum-based course timetabling format, from the Udine site, and
+/// the part of the XHSTT high school format that fits a curriculum-based instance.
+pub struct XmlFormat;
+
+impl InstanceFormat for XmlFormat {
+    fn name(&self) -> &str {
+        "xml"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xml"]
+    }
+
+    fn sniff(&self, head: &str) -> bool {
+        let head = head.trim_start();
+        head.starts_with("<?xml") || head.starts_with("<instance") ||
+        head.starts_with("<HighSchoolTimetableArchive")
+    }
+
+    fn load(&self, path: &str) -> TimetableDataResult {
+        Loader::new(path)?.load()
+    }
+}
+
+/// An element of an XML document.
+struct Element {
+    name: String,
+    /// The line the element's start tag is on.
+    line: usize,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    /// The text directly inside the element, with the whitespace around it trimmed.
+    text: String,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(other, _)| other == name).map(|(_, value)| &value[..])
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// The children with the specified name, skipping over any others.
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// The start tag of the element, for error messages.
+    fn tag(&self) -> String {
+        let mut tag = format!("<{}", self.name);
+        for (name, value) in self.attributes.iter() {
+            tag.push_str(&format!(" {}=\"{}\"", name, value));
+        }
+        tag.push('>');
+        tag.push_str(&self.text);
+        tag
+    }
+}
+
+/// Loads an instance from a Udine XML file, which looks like:
+///
+/// ```text
+/// <instance name="...">
+///   <descriptor>
+///     <days value="5"/> <periods_per_day value="6"/> <daily_lectures min="2" max="5"/>
+///   </descriptor>
+///   <courses> <course id=".." teacher=".." lectures="3" min_days="2" students="30"
+///                     double_lectures="no"/> ... </courses>
+///   <rooms> <room id=".." size="32" building=".."/> ... </rooms>
+///   <curricula> <curriculum id=".."> <course ref=".."/> ... </curriculum> ... </curricula>
+///   <constraints>
+///     <constraint type="period" course=".."> <timeslot day="0" period="1"/> ... </constraint>
+///     <constraint type="room" course=".."> <room ref=".."/> ... </constraint>
+///   </constraints>
+/// </instance>
+/// ```
+///
+/// As in the ectt format, a room constraint lists the rooms that are unsuitable for the course.
+///
+/// An XHSTT (high school) archive holding a single instance is also read. Its days are taken from
+/// the `<Day>` time groups, and each day has to have the same number of times. Resources have to
+/// be teachers, rooms or classes. Each event becomes a course with a lecture for each time of its
+/// duration, paired up as double lectures when it lasts longer than one time. A preassigned
+/// teacher teaches the course (or else it has a teacher of its own, as in the tim format), a
+/// preassigned room is the only room it can be held in, and each class becomes a curriculum of
+/// the events it attends. Clashes, and events or rooms left unassigned, are penalised in every
+/// instance, and required unavailable times of teachers and classes ban the timeslots for their
+/// events.
+///
+/// Any other kind of constraint, preassigned times, and teachers or classes left to be assigned
+/// are reported as unsupported rather than being left out of the instance.
+pub struct Loader {
+    instance: Instance,
+
+    path: String,
+    document: Element,
+}
+
+impl Loader {
+    pub fn new(path: &str) -> Result<Self, DataError> {
+        let mut contents = String::new();
+        std::fs::File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|error| {
+                DataError::Io {
+                    path: path.to_owned(),
+                    error,
+                }
+            })?;
+
+        let document = Parser::new(path, &contents).parse_document()?;
+        Ok(Loader {
+            instance: Instance::new(),
+            path: path.to_owned(),
+            document,
+        })
+    }
+
+    pub fn load(mut self) -> TimetableDataResult {
+        let document = std::mem::replace(&mut self.document,
+                                         Element {
+                                             name: String::new(),
+                                             line: 0,
+                                             attributes: Vec::new(),
+                                             children: Vec::new(),
+                                             text: String::new(),
+                                         });
+        if document.name == "HighSchoolTimetableArchive" {
+            return self.load_archive(&document);
+        }
+        if document.name != "instance" {
+            return Err(self.error(HEADER,
+                                  &document,
+                                  "an <instance> or <HighSchoolTimetableArchive> element"));
+        }
+
+        self.read_descriptor(&document)?;
+        // NOTE: Curricula and constraints refer to courses and rooms, so they are read
+        // after them.
+        let courses = self.section(COURSES, &document, "courses")?;
+        for course in self.children(COURSES, courses, "course")? {
+            self.read_course(course)?;
+        }
+        let rooms = self.section(ROOMS, &document, "rooms")?;
+        for room in self.children(ROOMS, rooms, "room")? {
+            self.read_room(room)?;
+        }
+        if let Some(curricula) = document.child("curricula") {
+            for curriculum in self.children(CURRICULA, curricula, "curriculum")? {
+                self.read_curriculum(curriculum)?;
+            }
+        }
+        if let Some(constraints) = document.child("constraints") {
+            self.read_constraints(constraints)?;
+        }
+
+        Ok(self.instance)
+    }
+
+    /// Read the name, the days and periods, and the daily lectures of the instance.
+    fn read_descriptor(&mut self, document: &Element) -> Result<(), DataError> {
+        let section = HEADER;
+        let name = self.attribute(section, document, "name")?.to_owned();
+        let descriptor = self.section(section, document, "descriptor")?;
+        let days: usize = self.parse(section, self.section(section, descriptor, "days")?, "value")?;
+        let periods: usize =
+            self.parse(section, self.section(section, descriptor, "periods_per_day")?, "value")?;
+        if let Some(daily_lectures) = descriptor.child("daily_lectures") {
+            let min: usize = self.parse(section, daily_lectures, "min")?;
+            let max: usize = self.parse(section, daily_lectures, "max")?;
+            self.instance.set_daily_lectures((min, max));
+        }
+
+        self.instance.set_name(name);
+        self.instance.set_timeslots(days, periods);
+        Ok(())
+    }
+
+    fn read_course(&mut self, course: &Element) -> Result<(), DataError> {
+        let section = COURSES;
+        let course_id = self.attribute(section, course, "id")?.to_owned();
+        let teacher_id = self.attribute(section, course, "teacher")?.to_owned();
+        let num_lectures: usize = self.parse(section, course, "lectures")?;
+        let min_days: usize = self.parse(section, course, "min_days")?;
+        let num_students: usize = self.parse(section, course, "students")?;
+        let double_lectures = match course.attribute("double_lectures") {
+            None | Some("no") => false,
+            Some("yes") => true,
+            Some(_) => {
+                return Err(self.error(section, course, "\"yes\" or \"no\" for double_lectures"))
+            }
+        };
+
+        self.instance.add_course(&course_id,
+                                 &teacher_id,
+                                 num_lectures,
+                                 min_days,
+                                 num_students,
+                                 double_lectures);
+        Ok(())
+    }
+
+    fn read_room(&mut self, room: &Element) -> Result<(), DataError> {
+        let section = ROOMS;
+        let room_id = self.attribute(section, room, "id")?.to_owned();
+        let capacity: usize = self.parse(section, room, "size")?;
+        let building_id = room.attribute("building").unwrap_or("").to_owned();
+
+        self.instance.add_room(Room::new(room_id, capacity, building_id));
+        Ok(())
+    }
+
+    fn read_curriculum(&mut self, curriculum: &Element) -> Result<(), DataError> {
+        let section = CURRICULA;
+        let curriculum_id = self.attribute(section, curriculum, "id")?.to_owned();
+
+        let mut course_ids = Vec::new();
+        for course in self.children(section, curriculum, "course")? {
+            let course_id = self.attribute(section, course, "ref")?;
+            let events = self.instance.mut_events_with_course_id(course_id);
+            if events.is_empty() {
+                return Err(self.error(section, course, "a course id from the courses"));
+            }
+            for event in events {
+                event.add_curriculum_id(curriculum_id.clone());
+            }
+            course_ids.push(course_id.to_owned());
+        }
+
+        self.instance.add_curriculum(Curriculum::new(curriculum_id, course_ids));
+        Ok(())
+    }
+
+    fn read_constraints(&mut self, constraints: &Element) -> Result<(), DataError> {
+        for constraint in self.children(CONSTRAINTS, constraints, "constraint")? {
+            match self.attribute(CONSTRAINTS, constraint, "type")? {
+                "period" => self.read_period_constraint(constraint)?,
+                "room" => self.read_room_constraint(constraint)?,
+                kind => {
+                    let found = format!("a \"{}\" constraint", kind);
+                    return Err(self.unsupported(CONSTRAINTS, constraint, &found));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_period_constraint(&mut self, constraint: &Element) -> Result<(), DataError> {
+        let section = UNAVAILABILITY_CONSTRAINTS;
+        let course_id = self.course(section, constraint)?.to_owned();
+
+        let mut banned_timeslots = Vec::new();
+        for timeslot in self.children(section, constraint, "timeslot")? {
+            let day: usize = self.parse(section, timeslot, "day")?;
+            let period: usize = self.parse(section, timeslot, "period")?;
+            if day >= self.instance.days() || period >= self.instance.periods_per_day() {
+                return Err(self.error(section,
+                                      timeslot,
+                                      "a day and period within the descriptor's bounds"));
+            }
+            banned_timeslots.push(TimeSlot::new(day, period));
+        }
+
+        for event in self.instance.mut_events_with_course_id(&course_id) {
+            for timeslot in banned_timeslots.iter() {
+                event.ban_timeslot(*timeslot);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a room constraint, banning the course from each of the rooms it lists.
+    fn read_room_constraint(&mut self, constraint: &Element) -> Result<(), DataError> {
+        let section = ROOM_CONSTRAINTS;
+        let course_id = self.course(section, constraint)?.to_owned();
+
+        let mut unsuitable_rooms = Vec::new();
+        for room in self.children(section, constraint, "room")? {
+            let room_id = self.attribute(section, room, "ref")?;
+            match self.instance.rooms().iter().position(|room| room.id() == room_id) {
+                Some(room_index) => unsuitable_rooms.push(room_index),
+                None => return Err(self.error(section, room, "a room id from the rooms")),
+            }
+        }
+
+        let num_rooms = self.instance.num_rooms();
+        for event in self.instance.mut_events_with_course_id(&course_id) {
+            for &room_index in unsuitable_rooms.iter() {
+                event.ban_room(room_index, num_rooms);
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the only instance of an XHSTT archive.
+    fn load_archive(mut self, archive: &Element) -> TimetableDataResult {
+        let section = HEADER;
+        let instances = self.section(section, archive, "Instances")?;
+        let instances = self.children(section, instances, "Instance")?;
+        let instance = match instances.len() {
+            0 => return Err(self.error(section, archive, "an <Instance> element inside")),
+            1 => &instances[0],
+            _ => {
+                return Err(self.unsupported(section,
+                                            &instances[1],
+                                            "an archive of more than one instance"))
+            }
+        };
+        let name = self.attribute(section, instance, "Id")?.to_owned();
+        self.instance.set_name(name);
+
+        // NOTE: Events refer to resources, and constraints refer to times, resources and the
+        // courses made from the events, so they are read in that order.
+        let times = self.read_times(self.section(TIMES, instance, "Times")?)?;
+        let resources =
+            self.read_resources(self.section(RESOURCES, instance, "Resources")?)?;
+        self.read_events(self.section(EVENTS, instance, "Events")?, &resources)?;
+        if let Some(constraints) = instance.child("Constraints") {
+            for constraint in constraints.children.iter() {
+                self.read_archive_constraint(constraint, &times, &resources)?;
+            }
+        }
+
+        Ok(self.instance)
+    }
+
+    /// Read the days and times of an archive's instance, finding the timeslot of each time and
+    /// the timeslots in each time group.
+    fn read_times(&mut self, times: &Element) -> Result<Times, DataError> {
+        let section = TIMES;
+        let mut day_ids = Vec::new();
+        let mut groups = HashMap::new();
+        if let Some(time_groups) = times.child("TimeGroups") {
+            for group in time_groups.children.iter() {
+                let group_id = self.attribute(section, group, "Id")?;
+                if group.name == "Day" {
+                    day_ids.push(group_id);
+                }
+                groups.insert(group_id.to_owned(), Vec::new());
+            }
+        }
+        if day_ids.is_empty() {
+            return Err(self.unsupported(section, times, "times that are not grouped into days"));
+        }
+
+        // The times on each day, in order.
+        let mut day_times = vec![Vec::new(); day_ids.len()];
+        for time in times.children_named("Time") {
+            let day = match time.child("Day") {
+                Some(day) => day,
+                None => return Err(self.unsupported(section, time, "a time that is not on a day")),
+            };
+            let day_id = self.attribute(section, day, "Reference")?;
+            match day_ids.iter().position(|&other| other == day_id) {
+                Some(day_index) => day_times[day_index].push(time),
+                None => return Err(self.error(section, day, "a day id from the time groups")),
+            }
+        }
+        let periods_per_day = day_times[0].len();
+        if day_times.iter().any(|times| times.len() != periods_per_day) {
+            return Err(self.unsupported(section, times, "days with different numbers of times"));
+        }
+        self.instance.set_timeslots(day_ids.len(), periods_per_day);
+
+        let mut timeslots = HashMap::new();
+        for (day, times) in day_times.into_iter().enumerate() {
+            for (period, time) in times.into_iter().enumerate() {
+                let timeslot = TimeSlot::new(day, period);
+                let mut references: Vec<&Element> =
+                    time.children.iter().filter(|child| child.name != "Name").collect();
+                if let Some(time_groups) = time.child("TimeGroups") {
+                    references.retain(|child| child.name != "TimeGroups");
+                    references.extend(time_groups.children.iter());
+                }
+                for reference in references {
+                    let group_id = self.attribute(section, reference, "Reference")?;
+                    match groups.get_mut(group_id) {
+                        Some(group) => group.push(timeslot),
+                        None => {
+                            return Err(self.error(section,
+                                                  reference,
+                                                  "a time group id from the time groups"))
+                        }
+                    }
+                }
+                timeslots.insert(self.attribute(section, time, "Id")?.to_owned(), timeslot);
+            }
+        }
+
+        Ok(Times {
+            timeslots,
+            groups,
+        })
+    }
+
+    /// Read the resources of an archive's instance, adding a room for each room resource.
+    fn read_resources(&mut self, resources: &Element) -> Result<Resources, DataError> {
+        let section = RESOURCES;
+        let mut types = HashMap::new();
+        if let Some(resource_types) = resources.child("ResourceTypes") {
+            for resource_type in resource_types.children.iter() {
+                let type_id = self.attribute(section, resource_type, "Id")?;
+                let name = resource_type.child("Name").map_or("", |name| &name.text[..]);
+                let kind =
+                    ResourceKind::from_name(type_id).or_else(|| ResourceKind::from_name(name));
+                types.insert(type_id.to_owned(), kind);
+            }
+        }
+        let mut groups = HashMap::new();
+        if let Some(resource_groups) = resources.child("ResourceGroups") {
+            for group in resource_groups.children.iter() {
+                groups.insert(self.attribute(section, group, "Id")?.to_owned(), Vec::new());
+            }
+        }
+
+        let mut kinds = HashMap::new();
+        for resource in resources.children_named("Resource") {
+            let resource_id = self.attribute(section, resource, "Id")?;
+            let kind = self.resource_kind(section, resource, &types)?;
+            if kind == ResourceKind::Room {
+                self.instance.add_room(Room::new(resource_id.to_owned(), 0, String::new()));
+            }
+            if let Some(resource_groups) = resource.child("ResourceGroups") {
+                for group in resource_groups.children.iter() {
+                    let group_id = self.attribute(section, group, "Reference")?;
+                    match groups.get_mut(group_id) {
+                        Some(group) => group.push(resource_id.to_owned()),
+                        None => {
+                            return Err(self.error(section,
+                                                  group,
+                                                  "a resource group id from the resource groups"))
+                        }
+                    }
+                }
+            }
+            kinds.insert(resource_id.to_owned(), kind);
+        }
+
+        Ok(Resources {
+            types,
+            kinds,
+            groups,
+        })
+    }
+
+    /// Read the events of an archive's instance as courses, and its classes as curricula.
+    fn read_events(&mut self, events: &Element, resources: &Resources) -> Result<(), DataError> {
+        let section = EVENTS;
+        // The courses attending each class.
+        let mut class_courses: Vec<(String, Vec<String>)> = Vec::new();
+
+        for event in events.children_named("Event") {
+            let course_id = self.attribute(section, event, "Id")?;
+            let duration: usize = self.parse_text(section, event, "Duration")?;
+            if let Some(time) = event.child("Time") {
+                return Err(self.unsupported(section, time, "an event with a preassigned time"));
+            }
+
+            let mut teacher_id = None;
+            let mut room_index = None;
+            let event_resources = event.child("Resources").map_or(&[][..], |list| &list.children);
+            for resource in event_resources {
+                let resource_id = match resource.attribute("Reference") {
+                    Some(resource_id) => resource_id,
+                    // Only rooms are assigned while solving, so any room will do.
+                    None => {
+                        match self.resource_kind(section, resource, &resources.types)? {
+                            ResourceKind::Room => continue,
+                            _ => {
+                                return Err(self.unsupported(section,
+                                                            resource,
+                                                            "a teacher or class left to be \
+                                                             assigned"))
+                            }
+                        }
+                    }
+                };
+                match resources.kinds.get(resource_id) {
+                    Some(&ResourceKind::Teacher) if teacher_id.is_none() => {
+                        teacher_id = Some(resource_id)
+                    }
+                    Some(&ResourceKind::Room) if room_index.is_none() => {
+                        room_index =
+                            self.instance.rooms().iter().position(|room| room.id() == resource_id)
+                    }
+                    Some(&ResourceKind::Class) => {
+                        match class_courses.iter().position(|(other, _)| other == resource_id) {
+                            Some(index) => class_courses[index].1.push(course_id.to_owned()),
+                            None => {
+                                class_courses.push((resource_id.to_owned(),
+                                                    vec![course_id.to_owned()]))
+                            }
+                        }
+                    }
+                    Some(_) => {
+                        return Err(self.unsupported(section,
+                                                    resource,
+                                                    "an event with more than one teacher or room"))
+                    }
+                    None => {
+                        let expected = "a resource id from the resources";
+                        return Err(self.error(section, resource, expected));
+                    }
+                }
+            }
+
+            let teacher_id = teacher_id.unwrap_or(course_id);
+            self.instance.add_course(course_id, teacher_id, duration, 1, 0, duration > 1);
+            if let Some(room_index) = room_index {
+                let num_rooms = self.instance.num_rooms();
+                for event in self.instance.mut_events_with_course_id(course_id) {
+                    for other_index in (0..num_rooms).filter(|&other| other != room_index) {
+                        event.ban_room(other_index, num_rooms);
+                    }
+                }
+            }
+        }
+
+        for (class_id, course_ids) in class_courses {
+            for course_id in course_ids.iter() {
+                for event in self.instance.mut_events_with_course_id(course_id) {
+                    event.add_curriculum_id(class_id.clone());
+                }
+            }
+            self.instance.add_curriculum(Curriculum::new(class_id, course_ids));
+        }
+        Ok(())
+    }
+
+    fn read_archive_constraint(&mut self,
+                               constraint: &Element,
+                               times: &Times,
+                               resources: &Resources)
+                               -> Result<(), DataError> {
+        match &constraint.name[..] {
+            // Every instance already penalises clashes, and unallocated events.
+            "AvoidClashesConstraint" |
+            "AssignTimeConstraint" |
+            "AssignResourceConstraint" => Ok(()),
+            "AvoidUnavailableTimesConstraint" => {
+                self.read_unavailable_times(constraint, times, resources)
+            }
+            kind => {
+                let found = format!("a <{}>", kind);
+                Err(self.unsupported(CONSTRAINTS, constraint, &found))
+            }
+        }
+    }
+
+    /// Read a required AvoidUnavailableTimesConstraint, banning the times from the events of
+    /// each teacher and class it applies to.
+    fn read_unavailable_times(&mut self,
+                              constraint: &Element,
+                              times: &Times,
+                              resources: &Resources)
+                              -> Result<(), DataError> {
+        let section = CONSTRAINTS;
+        if self.section(section, constraint, "Required")?.text != "true" {
+            let found = "a soft unavailable times constraint";
+            return Err(self.unsupported(section, constraint, found));
+        }
+
+        let mut banned_timeslots = Vec::new();
+        if let Some(list) = constraint.child("Times") {
+            for time in list.children.iter() {
+                match times.timeslots.get(self.attribute(section, time, "Reference")?) {
+                    Some(&timeslot) => banned_timeslots.push(timeslot),
+                    None => return Err(self.error(section, time, "a time id from the times")),
+                }
+            }
+        }
+        if let Some(list) = constraint.child("TimeGroups") {
+            for group in list.children.iter() {
+                match times.groups.get(self.attribute(section, group, "Reference")?) {
+                    Some(timeslots) => banned_timeslots.extend(timeslots.iter().cloned()),
+                    None => {
+                        return Err(self.error(section, group, "a time group id from the times"))
+                    }
+                }
+            }
+        }
+
+        let applies_to = self.section(section, constraint, "AppliesTo")?;
+        let mut resource_ids = Vec::new();
+        if let Some(list) = applies_to.child("Resources") {
+            for resource in list.children.iter() {
+                resource_ids.push(self.attribute(section, resource, "Reference")?);
+            }
+        }
+        if let Some(list) = applies_to.child("ResourceGroups") {
+            for group in list.children.iter() {
+                match resources.groups.get(self.attribute(section, group, "Reference")?) {
+                    Some(members) => resource_ids.extend(members.iter().map(|id| &id[..])),
+                    None => {
+                        return Err(self.error(section,
+                                              group,
+                                              "a resource group id from the resources"))
+                    }
+                }
+            }
+        }
+
+        for resource_id in resource_ids {
+            let mut course_ids: Vec<String> = match resources.kinds.get(resource_id) {
+                Some(&ResourceKind::Teacher) => {
+                    self.instance
+                        .events()
+                        .iter()
+                        .filter(|event| event.teacher() == resource_id)
+                        .map(|event| event.course_id().to_owned())
+                        .collect()
+                }
+                Some(&ResourceKind::Class) => {
+                    self.instance
+                        .curricula()
+                        .iter()
+                        .filter(|curriculum| curriculum.id() == resource_id)
+                        .flat_map(|curriculum| curriculum.course_ids().iter().cloned())
+                        .collect()
+                }
+                Some(&ResourceKind::Room) => {
+                    return Err(self.unsupported(section, constraint, "unavailable times of a room"))
+                }
+                None => {
+                    return Err(self.error(section, applies_to, "a resource id from the resources"))
+                }
+            };
+            course_ids.dedup();
+            for course_id in course_ids {
+                for event in self.instance.mut_events_with_course_id(&course_id) {
+                    for timeslot in banned_timeslots.iter() {
+                        event.ban_timeslot(*timeslot);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The kind of resource named by the <ResourceType> inside the element.
+    fn resource_kind(&self,
+                     section: &'static str,
+                     element: &Element,
+                     types: &HashMap<String, Option<ResourceKind>>)
+                     -> Result<ResourceKind, DataError> {
+        let resource_type = self.section(section, element, "ResourceType")?;
+        let type_id = self.attribute(section, resource_type, "Reference")?;
+        match types.get(type_id) {
+            Some(&Some(kind)) => Ok(kind),
+            Some(&None) => {
+                let found = format!("a resource of type \"{}\"", type_id);
+                Err(self.unsupported(section, resource_type, &found))
+            }
+            None => {
+                Err(self.error(section, resource_type, "a resource type id from the resources"))
+            }
+        }
+    }
+
+    /// The course a constraint is on, making sure it is a known course.
+    fn course<'a>(&self, section: &'static str, constraint: &'a Element) -> Result<&'a str, DataError> {
+        let course_id = self.attribute(section, constraint, "course")?;
+        if !self.instance.events().iter().any(|event| event.course_id() == course_id) {
+            return Err(self.error(section, constraint, "a course id from the courses"));
+        }
+        Ok(course_id)
+    }
+
+    /// Find an element that has to be there.
+    fn section<'a>(&self,
+                   section: &'static str,
+                   parent: &'a Element,
+                   name: &str)
+                   -> Result<&'a Element, DataError> {
+        parent.child(name).ok_or_else(|| {
+            self.error(section, parent, &format!("a <{}> element inside", name))
+        })
+    }
+
+    /// The children of an element, making sure they are all the expected kind of element.
+    fn children<'a>(&self,
+                    section: &'static str,
+                    parent: &'a Element,
+                    name: &str)
+                    -> Result<&'a [Element], DataError> {
+        match parent.children.iter().find(|child| child.name != name) {
+            Some(child) => Err(self.error(section, child, &format!("a <{}> element", name))),
+            None => Ok(&parent.children),
+        }
+    }
+
+    /// Make sure an attribute of the element was actually there.
+    fn attribute<'a>(&self,
+                     section: &'static str,
+                     element: &'a Element,
+                     name: &str)
+                     -> Result<&'a str, DataError> {
+        element.attribute(name).ok_or_else(|| {
+            self.error(section, element, &format!("a \"{}\" attribute", name))
+        })
+    }
+
+    /// Parse an attribute of the element into a value.
+    fn parse<T: FromStr>(&self,
+                         section: &'static str,
+                         element: &Element,
+                         name: &str)
+                         -> Result<T, DataError> {
+        let value = self.attribute(section, element, name)?;
+        value.parse().map_err(|_| {
+            self.error(section, element, &format!("a number for the \"{}\" attribute", name))
+        })
+    }
+
+    /// Parse the text inside a child of the element into a value.
+    fn parse_text<T: FromStr>(&self,
+                              section: &'static str,
+                              element: &Element,
+                              name: &str)
+                              -> Result<T, DataError> {
+        let child = self.section(section, element, name)?;
+        child.text.parse().map_err(|_| {
+            self.error(section, child, &format!("a number inside <{}>", name))
+        })
+    }
+
+    /// Build an error for the specified element.
+    fn error(&self, section: &'static str, element: &Element, expected: &str) -> DataError {
+        DataError::Parse {
+            path: self.path.clone(),
+            line: element.line,
+            section,
+            expected: expected.to_owned(),
+            found: element.tag(),
+        }
+    }
+
+    fn unsupported(&self, section: &'static str, element: &Element, found: &str) -> DataError {
+        DataError::Unsupported {
+            path: self.path.clone(),
+            line: element.line,
+            section,
+            found: found.to_owned(),
+        }
+    }
+}
+
+/// The timeslot of each time in an XHSTT instance, and the timeslots in each time group, by id.
+struct Times {
+    timeslots: HashMap<String, TimeSlot>,
+    groups: HashMap<String, Vec<TimeSlot>>,
+}
+
+/// The kinds of XHSTT resource that fit a curriculum-based instance.
+#[derive(Clone, Copy, PartialEq)]
+enum ResourceKind {
+    Teacher,
+    Room,
+    Class,
+}
+
+impl ResourceKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match &name.to_lowercase()[..] {
+            "teacher" | "teachers" => Some(ResourceKind::Teacher),
+            "room" | "rooms" => Some(ResourceKind::Room),
+            "class" | "classes" => Some(ResourceKind::Class),
+            _ => None,
+        }
+    }
+}
+
+/// The resource types, resources and resource groups of an XHSTT instance, by id.
+struct Resources {
+    /// The kind of resource of each type, or None if it is not a kind that is supported.
+    types: HashMap<String, Option<ResourceKind>>,
+    kinds: HashMap<String, ResourceKind>,
+    /// The resources in each group.
+    groups: HashMap<String, Vec<String>>,
+}
+
+/// Reads the elements out of an XML document. Declarations, comments and CDATA are skipped.
+struct Parser<'a> {
+    path: &'a str,
+    contents: &'a str,
+    /// The byte offset of the next character to be read.
+    position: usize,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(path: &'a str, contents: &'a str) -> Self {
+        Parser {
+            path,
+            contents,
+            position: 0,
+            line: 1,
+        }
+    }
+
+    fn parse_document(&mut self) -> Result<Element, DataError> {
+        self.skip_misc()?;
+        let document = self.parse_element()?;
+        self.skip_misc()?;
+        if self.position < self.contents.len() {
+            return Err(self.error("the end of the document"));
+        }
+        Ok(document)
+    }
+
+    /// Skip the whitespace, declarations and comments around the document's element.
+    fn skip_misc(&mut self) -> Result<(), DataError> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_element(&mut self) -> Result<Element, DataError> {
+        let line = self.line;
+        self.expect("<")?;
+        let name = self.parse_name()?;
+        let mut element = Element {
+            name,
+            line,
+            attributes: Vec::new(),
+            children: Vec::new(),
+            text: String::new(),
+        };
+
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.advance(2);
+                return Ok(element);
+            }
+            if self.rest().starts_with(">") {
+                self.advance(1);
+                break;
+            }
+            let attribute = self.parse_name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let value = self.parse_value()?;
+            element.attributes.push((attribute, value));
+        }
+
+        // Read the content of the element up to its end tag.
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.advance(2);
+                let name = self.parse_name()?;
+                if name != element.name {
+                    return Err(DataError::Parse {
+                        path: self.path.to_owned(),
+                        line: self.line,
+                        section: MARKUP,
+                        expected: format!("</{}>", element.name),
+                        found: format!("</{}>", name),
+                    });
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                element.text = element.text.trim().to_owned();
+                return Ok(element);
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.skip_past("]]>")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<") {
+                let child = self.parse_element()?;
+                element.children.push(child);
+            } else if rest.is_empty() {
+                return Err(self.eof(&format!("</{}>", element.name)));
+            } else {
+                let length = rest.find('<').unwrap_or(rest.len());
+                element.text.push_str(&unescape(&rest[..length]));
+                self.advance(length);
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, DataError> {
+        let length = self.rest()
+            .find(|c: char| c.is_whitespace() || "/>=\"'".contains(c))
+            .unwrap_or(self.rest().len());
+        if length == 0 {
+            return Err(self.error("a name"));
+        }
+        let name = self.rest()[..length].to_owned();
+        self.advance(length);
+        Ok(name)
+    }
+
+    /// Read a quoted attribute value, replacing any entities in it.
+    fn parse_value(&mut self) -> Result<String, DataError> {
+        let quote = match self.rest().chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => quote,
+            _ => return Err(self.error("a quoted value")),
+        };
+        self.advance(1);
+        let length = match self.rest().find(quote) {
+            Some(length) => length,
+            None => return Err(self.eof(&format!("a closing {}", quote))),
+        };
+        let value = unescape(&self.rest()[..length]);
+        self.advance(length + 1);
+        Ok(value)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), DataError> {
+        if self.rest().starts_with(expected) {
+            self.advance(expected.len());
+            Ok(())
+        } else if self.rest().is_empty() {
+            Err(self.eof(expected))
+        } else {
+            Err(self.error(expected))
+        }
+    }
+
+    /// Skip up to and including the end marker.
+    fn skip_past(&mut self, end: &str) -> Result<(), DataError> {
+        match self.rest().find(end) {
+            Some(length) => {
+                self.advance(length + end.len());
+                Ok(())
+            }
+            None => Err(self.eof(end)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        let length = rest.len() - rest.trim_start().len();
+        self.advance(length);
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.contents[self.position..]
+    }
+
+    /// Move past the next length bytes, keeping count of the lines.
+    fn advance(&mut self, length: usize) {
+        let skipped = &self.contents[self.position..self.position + length];
+        self.line += skipped.matches('\n').count();
+        self.position += length;
+    }
+
+    fn error(&self, expected: &str) -> DataError {
+        let found = self.rest().lines().next().unwrap_or("").trim();
+        DataError::Parse {
+            path: self.path.to_owned(),
+            line: self.line,
+            section: MARKUP,
+            expected: expected.to_owned(),
+            found: found.to_owned(),
+        }
+    }
+
+    fn eof(&self, expected: &str) -> DataError {
+        DataError::UnexpectedEof {
+            path: self.path.to_owned(),
+            section: MARKUP,
+            expected: expected.to_owned(),
+        }
+    }
+}
+
+/// Replace the entities in text or an attribute value.
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use data::{Instance, Event, TimeSlot, DataError};
+    use super::*;
+
+    /// Load the contents from a file in the temporary directory.
+    fn load(name: &str, contents: &str) -> TimetableDataResult {
+        let path = std::env::temp_dir()
+            .join(format!("time_table_{}_{}.xml", std::process::id(), name));
+        let path = path.to_str().expect("Temporary path is not valid unicode.");
+        std::fs::File::create(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .expect("Could not write the test file.");
+        let result = XmlFormat.load(path);
+        let _ = std::fs::remove_file(path);
+        result
+    }
+
+    fn course_events<'a>(instance: &'a Instance, course_id: &'a str) -> Vec<&'a Event> {
+        instance.events().iter().filter(|event| event.course_id() == course_id).collect()
+    }
+
+    #[test]
+    fn room_constraints_ban_the_listed_rooms() {
+        let instance = load("room_constraints",
+                            r#"<instance name="Rooms">
+  <descriptor> <days value="2"/> <periods_per_day value="2"/> </descriptor>
+  <courses>
+    <course id="C0" teacher="T0" lectures="2" min_days="1" students="10"/>
+    <course id="C1" teacher="T1" lectures="1" min_days="1" students="10"/>
+  </courses>
+  <rooms> <room id="R0" size="20"/> <room id="R1" size="20"/> <room id="R2" size="20"/> </rooms>
+  <constraints>
+    <constraint type="room" course="C0"> <room ref="R0"/> </constraint>
+    <constraint type="room" course="C0"> <room ref="R2"/> </constraint>
+  </constraints>
+</instance>"#)
+            .unwrap();
+
+        for event in course_events(&instance, "C0") {
+            assert_eq!(event.valid_rooms(), Some(&[1][..]));
+        }
+        assert_eq!(course_events(&instance, "C1")[0].valid_rooms(), None);
+    }
+
+    #[test]
+    fn loads_an_xhstt_archive() {
+        const ARCHIVE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<HighSchoolTimetableArchive Id="Archive">
+  <Instances>
+    <Instance Id="School">
+      <Times>
+        <TimeGroups> <Day Id="Mo"/> <Day Id="Tu"/> <TimeGroup Id="Mornings"/> </TimeGroups>
+        <Time Id="Mo1"> <Day Reference="Mo"/>
+          <TimeGroups> <TimeGroup Reference="Mornings"/> </TimeGroups> </Time>
+        <Time Id="Mo2"> <Day Reference="Mo"/> </Time>
+        <Time Id="Tu1"> <Day Reference="Tu"/>
+          <TimeGroups> <TimeGroup Reference="Mornings"/> </TimeGroups> </Time>
+        <Time Id="Tu2"> <Day Reference="Tu"/> </Time>
+      </Times>
+      <Resources>
+        <ResourceTypes>
+          <ResourceType Id="T"> <Name>Teacher</Name> </ResourceType>
+          <ResourceType Id="Room"/> <ResourceType Id="Class"/>
+        </ResourceTypes>
+        <Resource Id="Smith"> <ResourceType Reference="T"/> </Resource>
+        <Resource Id="Lab"> <ResourceType Reference="Room"/> </Resource>
+        <Resource Id="Hall"> <ResourceType Reference="Room"/> </Resource>
+        <Resource Id="7A"> <ResourceType Reference="Class"/> </Resource>
+      </Resources>
+      <Events>
+        <Event Id="Maths"> <Duration>2</Duration>
+          <Resources>
+            <Resource Reference="Smith"/> <Resource Reference="7A"/>
+            <Resource> <ResourceType Reference="Room"/> </Resource>
+          </Resources>
+        </Event>
+        <Event Id="Science"> <Duration>1</Duration>
+          <Resources> <Resource Reference="7A"/> <Resource Reference="Hall"/> </Resources>
+        </Event>
+      </Events>
+      <Constraints>
+        <AvoidClashesConstraint Id="Clashes"> <Required>true</Required> </AvoidClashesConstraint>
+        <AvoidUnavailableTimesConstraint Id="Smith mornings"> <Required>true</Required>
+          <AppliesTo> <Resources> <Resource Reference="Smith"/> </Resources> </AppliesTo>
+          <TimeGroups> <TimeGroup Reference="Mornings"/> </TimeGroups>
+        </AvoidUnavailableTimesConstraint>
+      </Constraints>
+    </Instance>
+  </Instances>
+</HighSchoolTimetableArchive>"#;
+        let instance = load("archive", ARCHIVE).unwrap();
+
+        assert_eq!(instance.name(), Some("School"));
+        assert_eq!((instance.days(), instance.periods_per_day()), (2, 2));
+        assert_eq!(instance.num_rooms(), 2);
+
+        let maths = course_events(&instance, "Maths");
+        assert_eq!(maths.len(), 2);
+        assert_eq!(maths[0].teacher(), "Smith");
+        assert_eq!(maths[0].double_lecture_partner(), Some(1));
+        assert_eq!(maths[0].valid_rooms(), None);
+        assert!(maths[0].banned_timeslots() == &[TimeSlot::new(0, 0), TimeSlot::new(1, 0)][..]);
+
+        let science = course_events(&instance, "Science");
+        assert_eq!(science.len(), 1);
+        assert_eq!(science[0].teacher(), "Science");
+        assert_eq!(science[0].valid_rooms(), Some(&[1][..]));
+        assert!(science[0].banned_timeslots().is_empty());
+
+        assert_eq!(instance.curricula().len(), 1);
+        assert_eq!(instance.curricula()[0].id(), "7A");
+        assert_eq!(instance.curricula()[0].course_ids(), &["Maths", "Science"]);
+        assert_eq!(maths[0].curriculum_ids(), &["7A"]);
+
+        // A soft unavailable times constraint can't be banned, so it is reported.
+        let soft = ARCHIVE.replace("<Required>true</Required>\n", "<Required>false</Required>\n");
+        match load("soft_archive", &soft) {
+            Err(DataError::Unsupported { .. }) => {}
+            result => panic!("Expected an unsupported constraint, got {:?}", result.err()),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+
+use candidate::*;
+use data::*;
+
+/// The penalty a candidate incurs from one constraint.
+pub struct ConstraintCost {
+    pub name: String,
+    pub scope: Scope,
+    pub kind: ConstraintKind,
+    /// The number of times the constraint was violated.
+    pub violations: usize,
+    /// The violations multiplied by the weight of the constraint.
+    pub penalty: usize,
+}
+
+/// A full account of where a candidate's cost comes from.
+pub struct CostBreakdown {
+    constraints: Vec<ConstraintCost>,
+    unallocated_events: usize,
+}
+
+impl CostBreakdown {
+    /// The cost of each constraint, in the order they were checked.
+    pub fn constraints(&self) -> &[ConstraintCost] {
+        &self.constraints
+    }
+
+    /// The number of events that were not allocated. Each counts as a hard violation.
+    pub fn unallocated_events(&self) -> usize {
+        self.unallocated_events
+    }
+
+    /// The total cost of the candidate.
+    pub fn total(&self) -> Cost {
+        let mut cost = Cost::zero();
+        cost.add(ConstraintKind::Hard, self.unallocated_events);
+        for constraint in self.constraints.iter() {
+            cost.add(constraint.kind, constraint.penalty);
+        }
+        cost
+    }
+
+    /// Record the violations of a constraint, merging them with any constraint of the same name.
+    fn add(&mut self,
+           name: &str,
+           scope: Scope,
+           kind: ConstraintKind,
+           violations: usize,
+           weight: usize) {
+        if let Some(constraint) = self.constraints.iter_mut().find(|other| other.name == name) {
+            constraint.violations += violations;
+            constraint.penalty += violations * weight;
+            return;
+        }
+        self.constraints.push(ConstraintCost {
+            name: name.to_owned(),
+            scope,
+            kind,
+            violations,
+            penalty: violations * weight,
+        });
+    }
+}
+
+impl fmt::Display for CostBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Unallocated events (Hard): {}", self.unallocated_events)?;
+        for constraint in self.constraints.iter() {
+            writeln!(f,
+                     "{} ({:?}, {:?}): {} violations, penalty {}",
+                     constraint.name,
+                     constraint.kind,
+                     constraint.scope,
+                     constraint.violations,
+                     constraint.penalty)?;
+        }
+        let total = self.total();
+        write!(f, "Total: {} hard, {} soft", total.hard, total.soft)
+    }
+}
+
+/// Evaluate every constraint against the candidate from scratch, rather than relying on the
+/// costs kept up to date as it was allocated.
+pub fn evaluate(candidate: &CandidateSolution, instance: &Instance) -> CostBreakdown {
+    let mut breakdown = CostBreakdown {
+        constraints: Vec::new(),
+        unallocated_events: candidate.num_unallocated_events(),
+    };
+
+    let timetable = candidate.timetable();
+
+    // Constraints on single allocations:
+    for allocation in candidate.allocations() {
+        let event = instance.event(allocation.event_index()).expect("Invalid event_index.");
+        let allocation_index = (allocation.timeslot_index(), allocation.room_index());
+        for constraint in event.constraints().iter().chain(instance.constraints().iter()) {
+            let violations = constraint.check_for_violations(&timetable, allocation_index, instance);
+            breakdown.add(constraint.name(),
+                          Scope::Cell,
+                          constraint.kind(),
+                          violations,
+                          constraint.weight());
+        }
+    }
+
+    // Constraints on groups of events:
+    for constraint in instance.group_constraints().iter() {
+        let scope = constraint.scope();
+        for group in scope.groups(instance) {
+            let violations = constraint.check_group(&timetable, &group, instance);
+            breakdown.add(constraint.name(),
+                          scope,
+                          constraint.kind(),
+                          violations,
+                          constraint.weight());
+        }
+    }
+
+    breakdown
+}
@@ -77,7 +77,7 @@ impl AllocationStrategy for GeneticStrategy {
             }
             // --- Make way for the next generation!
             candidates = children.into_boxed_slice();
-            candidates.sort_by(|a, b| a.violations().cmp(&b.violations()));
+            candidates.sort_by_key(|candidate| candidate.cost());
             self.generations -= 1;
 
             // --- Early exit if solution is found.
@@ -87,7 +87,7 @@ impl AllocationStrategy for GeneticStrategy {
                 break;
             }
         }
-        // --- Return the list of candidates, sorted by number of violations
+        // --- Return the list of candidates, sorted by cost (hard violations first)
         candidates
     }
 }
@@ -112,7 +112,7 @@ fn tournament_selection<'a, RNG: Rng>(k: usize,
     let mut best: Option<&CandidateSolution> = None;
     for _ in 0..k {
         let temp = rng.choose(population).unwrap();
-        if best.is_none() || temp.violations() < best.unwrap().violations() {
+        if best.is_none() || temp.cost() < best.unwrap().cost() {
             best = Some(temp);
         }
     }
@@ -139,38 +139,29 @@ fn generate_n_random_candidates<RNG: Rng>(n: usize,
                 (instance.event(index).expect("Unexpected event index!"), index)
             };
 
-            // Find the set of valid timeslots for this event.
-            let mut valid_timeslots: Vec<TimeSlot> = instance.timeslots()
-                .iter()
-                // Filter out any banned timeslots.
-                .filter(|timeslot| !event.banned_timeslots().contains(timeslot))
-                // Filter out any timeslots that will cause a teacher conflict.
-                .filter(|timeslot| {
-                    let timeslot_index = instance.to_timeslot_index(timeslot.day, timeslot.period);
-                    for room_index in 0..instance.num_rooms() {
-                        if let Some(allocation) = candidate.get_allocation(timeslot_index, room_index) {
-                            let teacher = instance.event(allocation.event_index()).unwrap().teacher();
-                            if teacher == event.teacher() {
-                                return false; // don't include this.
-                            }
-                        }
-                    }
-                    // If we have gotten here, it's fine.
-                    true
-                })
-                .map(|timeslot| *timeslot)
-                .collect();
+            // Try to allocate a double lecture as a pair, before falling back to allocating its
+            // events one at a time.
+            let partner_index = event.double_lecture_partner()
+                .filter(|_| instance.formulation().has_double_lectures());
+            if let Some(partner_index) = partner_index {
+                if events_indicies.contains(&partner_index) &&
+                   allocate_double_lecture(event_index,
+                                           partner_index,
+                                           &mut candidate,
+                                           rng,
+                                           instance) {
+                    events_indicies.retain(|&index| index != partner_index);
+                    continue;
+                }
+            }
 
+            let mut valid_timeslots = valid_timeslots(event_index, &candidate, instance);
             if valid_timeslots.len() == 0 {
                 // TODO(zac): Ideally we'd just throw this out and start again.
                 valid_timeslots = instance.timeslots().to_owned();
             }
 
-            let valid_rooms: Vec<usize> = if event.valid_rooms().is_some() {
-                event.valid_rooms().unwrap().to_owned()
-            } else {
-                util::vec_from_range(0..instance.num_rooms())
-            };
+            let valid_rooms = valid_rooms(event_index, instance);
 
             'allocate: while !valid_timeslots.is_empty() {
                 let timeslot_index = {
@@ -204,6 +195,87 @@ fn generate_n_random_candidates<RNG: Rng>(n: usize,
     candidates.into_boxed_slice()
 }
 
+/// Allocate the two events of a double lecture to a random pair of consecutive timeslots, in a
+/// room that is free for both. Returns false if no such place could be found.
+fn allocate_double_lecture<RNG: Rng>(first_index: usize,
+                                     second_index: usize,
+                                     candidate: &mut CandidateSolution,
+                                     rng: &mut RNG,
+                                     instance: &Instance)
+                                     -> bool {
+    let second_timeslots = valid_timeslots(second_index, candidate, instance);
+    let mut first_timeslots: Vec<usize> = valid_timeslots(first_index, candidate, instance)
+        .iter()
+        .map(|timeslot| instance.to_timeslot_index(timeslot.day, timeslot.period))
+        .filter(|&timeslot_index| {
+            instance.timeslot(timeslot_index + 1)
+                .is_some_and(|next| {
+                    instance.are_consecutive(timeslot_index, timeslot_index + 1) &&
+                    second_timeslots.contains(next)
+                })
+        })
+        .collect();
+    let second_rooms = valid_rooms(second_index, instance);
+    let rooms: Vec<usize> = valid_rooms(first_index, instance)
+        .into_iter()
+        .filter(|room_index| second_rooms.contains(room_index))
+        .collect();
+
+    while !first_timeslots.is_empty() {
+        let timeslot_index = {
+            let timeslot_index_index = rng.gen_range(0, first_timeslots.len());
+            first_timeslots.remove(timeslot_index_index)
+        };
+        let mut rooms = rooms.clone();
+        while !rooms.is_empty() {
+            let room_index = {
+                let room_index_index = rng.gen_range(0, rooms.len());
+                rooms.remove(room_index_index)
+            };
+            if candidate.get_allocation(timeslot_index, room_index).is_none() &&
+               candidate.get_allocation(timeslot_index + 1, room_index).is_none() {
+                candidate.allocate_event(timeslot_index, room_index, Some(first_index), instance);
+                candidate.allocate_event(timeslot_index + 1,
+                                         room_index,
+                                         Some(second_index),
+                                         instance);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// The timeslots the event can be allocated to without being banned from it or causing a
+/// teacher conflict.
+fn valid_timeslots(event_index: usize,
+                   candidate: &CandidateSolution,
+                   instance: &Instance)
+                   -> Vec<TimeSlot> {
+    let event = instance.event(event_index).expect("Unexpected event index!");
+    instance.timeslots()
+        .iter()
+        // Filter out any banned timeslots.
+        .filter(|timeslot| !event.banned_timeslots().contains(timeslot))
+        // Filter out any timeslots that will cause a teacher conflict.
+        .filter(|timeslot| {
+            let timeslot_index = instance.to_timeslot_index(timeslot.day, timeslot.period);
+            candidate.teacher_lectures(timeslot_index, event_index) == 0
+        })
+        .copied()
+        .collect()
+}
+
+/// The rooms the event can be allocated to.
+fn valid_rooms(event_index: usize, instance: &Instance) -> Vec<usize> {
+    let event = instance.event(event_index).expect("Unexpected event index!");
+    if event.valid_rooms().is_some() {
+        event.valid_rooms().unwrap().to_owned()
+    } else {
+        util::vec_from_range(0..instance.num_rooms())
+    }
+}
+
 /// Creates children of the candidate solutions.
 fn crossover<RNG: Rng>(a: &CandidateSolution,
                        b: &CandidateSolution,
@@ -232,6 +304,13 @@ fn crossover<RNG: Rng>(a: &CandidateSolution,
             candidate_b.allocate_event(x, y, event_index, instance);
         }
     }
+    // Copying the cells across can leave a double lecture on either side of the crossover point.
+    if instance.formulation().has_double_lectures() {
+        for event_index in 0..instance.num_events() {
+            rejoin_double_lecture(&mut candidate_a, event_index, instance);
+            rejoin_double_lecture(&mut candidate_b, event_index, instance);
+        }
+    }
     (candidate_a, candidate_b)
 }
 
@@ -252,6 +331,52 @@ fn mutate<RNG: Rng>(candidate: &mut CandidateSolution, rng: &mut RNG, instance:
         (event1, event2)
     };
     // Swap places.
-    candidate.allocate_event(index1.0, index1.1, event2, instance);
-    candidate.allocate_event(index2.0, index2.1, event1, instance);
+    candidate.swap(index1, index2, instance);
+
+    // Bring any double lectures that were split up back together.
+    if instance.formulation().has_double_lectures() {
+        for event_index in event1.into_iter().chain(event2) {
+            rejoin_double_lecture(candidate, event_index, instance);
+        }
+    }
+}
+
+/// Move the double lecture partner of the event (if it has one) to the period before or after
+/// the event, in the same room. Whatever was there takes the partner's old place.
+fn rejoin_double_lecture(candidate: &mut CandidateSolution,
+                         event_index: usize,
+                         instance: &Instance) {
+    let partner_index = match instance.event(event_index)
+        .and_then(|event| event.double_lecture_partner()) {
+        Some(partner_index) => partner_index,
+        None => return,
+    };
+    let (event_allocation, partner_allocation) =
+        match (candidate.allocation_index_of(event_index),
+               candidate.allocation_index_of(partner_index)) {
+            (Some(event_allocation), Some(partner_allocation)) => {
+                (event_allocation, partner_allocation)
+            }
+            _ => return,
+        };
+    let (timeslot_index, room_index) = event_allocation;
+
+    // Nothing to do if the pair is already together.
+    let together = partner_allocation.1 == room_index &&
+                   (instance.are_consecutive(timeslot_index, partner_allocation.0) ||
+                    instance.are_consecutive(partner_allocation.0, timeslot_index));
+    if together {
+        return;
+    }
+
+    let target = if instance.are_consecutive(timeslot_index, timeslot_index + 1) {
+        (timeslot_index + 1, room_index)
+    } else if timeslot_index > 0 && instance.are_consecutive(timeslot_index - 1, timeslot_index) {
+        (timeslot_index - 1, room_index)
+    } else {
+        return;
+    };
+
+    // Swap the partner with whatever is at the target.
+    candidate.swap(partner_allocation, target, instance);
 }
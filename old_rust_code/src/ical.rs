@@ -0,0 +1,445 @@
+use std;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use candidate::CandidateSolution;
+use data::*;
+use render::View;
+
+/// A calendar date.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn new(year: i64, month: u32, day: u32) -> Self {
+        Date {
+            year,
+            month,
+            day,
+        }
+    }
+
+    /// The date the specified number of days after this one.
+    pub fn add_days(&self, days: i64) -> Date {
+        from_days(to_days(*self) + days)
+    }
+}
+
+/// A time of day, on a 24 hour clock.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Time {
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl Time {
+    pub fn new(hour: u32, minute: u32) -> Self {
+        Time {
+            hour,
+            minute,
+        }
+    }
+}
+
+/// When the days and periods of an instance fall in real time.
+pub struct Term {
+    /// The date of day 0 of the instance. Each following day of the instance is the next date.
+    pub start: Date,
+    /// The start and end time of each period of the day.
+    pub periods: Vec<(Time, Time)>,
+    /// The number of weeks the timetable repeats for.
+    pub weeks: usize,
+}
+
+/// Which export of a timetable this is.
+pub struct Revision<'a> {
+    /// Written as the SEQUENCE of each event. It should go up with each export of the timetable,
+    /// so that calendars take the lectures in it as newer than the ones they have.
+    pub sequence: u32,
+    /// The time of the export, in seconds since 1970-01-01 UTC.
+    pub time: i64,
+    /// The timetable as it was last exported, if it was. A lecture that has left a calendar
+    /// since then is written to it again as cancelled.
+    pub previous: Option<&'a CandidateSolution>,
+}
+
+impl<'a> Revision<'a> {
+    /// A revision exported now.
+    pub fn new(sequence: u32, previous: Option<&'a CandidateSolution>) -> Self {
+        Revision {
+            sequence,
+            time: now(),
+            previous,
+        }
+    }
+}
+
+/// Write an iCalendar (.ics) file for every room, teacher and curriculum into the directory,
+/// returning the paths of the files written.
+///
+/// Each event keeps the same UID whichever timeslot or room it is allocated to, so importing the
+/// files again after an alteration moves the existing calendar entries rather than duplicating
+/// them. Times are written as floating local times, without a timezone.
+pub fn export(directory: &str,
+              candidate: &CandidateSolution,
+              instance: &Instance,
+              term: &Term,
+              revision: &Revision)
+              -> std::io::Result<Vec<PathBuf>> {
+    if term.periods.len() != instance.periods_per_day() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                                       format!("expected a time for each of the {} periods, \
+                                                but {} were given",
+                                               instance.periods_per_day(),
+                                               term.periods.len())));
+    }
+
+    let mut paths = Vec::new();
+    for view in View::all(instance) {
+        let (kind, id) = match view {
+            View::Room(room_index) => ("room", instance.rooms()[room_index].id().to_owned()),
+            View::Teacher(ref teacher) => ("teacher", teacher.clone()),
+            View::Curriculum(curriculum_index) => {
+                ("curriculum", instance.curricula()[curriculum_index].id().to_owned())
+            }
+        };
+        let path = Path::new(directory).join(format!("{}-{}.ics", kind, file_name(&id)));
+        let file = std::fs::File::create(&path)?;
+        let mut writer = std::io::BufWriter::new(file);
+        write(&mut writer, candidate, instance, term, revision, &view)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Write the lectures shown in the view as an iCalendar, along with the lectures the view showed
+/// in the previous revision that it no longer does, as cancelled.
+pub fn write<W: Write>(writer: &mut W,
+                       candidate: &CandidateSolution,
+                       instance: &Instance,
+                       term: &Term,
+                       revision: &Revision,
+                       view: &View)
+                       -> std::io::Result<()> {
+    let instance_name = instance.name().unwrap_or("timetable");
+    // The (event, timeslot, room) of each lecture in the view, in event order.
+    let shown = |candidate: &CandidateSolution| {
+        let mut lectures: Vec<(usize, (usize, usize))> = candidate.allocations()
+            .iter()
+            .filter(|allocation| {
+                let event = instance.event(allocation.event_index())
+                    .expect("Invalid event index found in an allocation.");
+                view.shows(event, allocation.room_index(), instance)
+            })
+            .map(|allocation| {
+                (allocation.event_index(), (allocation.timeslot_index(), allocation.room_index()))
+            })
+            .collect();
+        lectures.sort();
+        lectures
+    };
+    let lectures = shown(candidate);
+    let cancelled: Vec<(usize, (usize, usize))> = match revision.previous {
+        Some(previous) => {
+            shown(previous)
+                .into_iter()
+                .filter(|&(event_index, _)| {
+                    !lectures.iter().any(|&(other, _)| other == event_index)
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    write_line(writer, "BEGIN:VCALENDAR")?;
+    write_line(writer, "VERSION:2.0")?;
+    write_line(writer, "PRODID:-//time_table//University Timetable//EN")?;
+    write_line(writer, "CALSCALE:GREGORIAN")?;
+    write_line(writer, "METHOD:PUBLISH")?;
+    write_line(writer,
+               &format!("X-WR-CALNAME:{}",
+                        escape(&format!("{} ({})", view.title(instance), instance_name))))?;
+
+    for &(event_index, cell) in lectures.iter() {
+        write_event(writer, instance, term, revision, event_index, cell, false)?;
+    }
+    for &(event_index, cell) in cancelled.iter() {
+        write_event(writer, instance, term, revision, event_index, cell, true)?;
+    }
+
+    write_line(writer, "END:VCALENDAR")?;
+    Ok(())
+}
+
+/// Write the event allocated to the (timeslot, room) cell as a VEVENT.
+fn write_event<W: Write>(writer: &mut W,
+                         instance: &Instance,
+                         term: &Term,
+                         revision: &Revision,
+                         event_index: usize,
+                         (timeslot_index, room_index): (usize, usize),
+                         cancelled: bool)
+                         -> std::io::Result<()> {
+    let event = instance.event(event_index).expect("Invalid event index found in an allocation.");
+    let room = instance.room(room_index).expect("Invalid room index found in an allocation.");
+    let timeslot = instance.timeslot(timeslot_index)
+        .expect("Invalid timeslot index found in an allocation.");
+    let stamp = utc_date_time(revision.time);
+    let date = term.start.add_days(timeslot.day as i64);
+    let (start, end) = term.periods[timeslot.period];
+
+    write_line(writer, "BEGIN:VEVENT")?;
+    write_line(writer, &format!("UID:{}", uid(instance, event_index)))?;
+    write_line(writer, &format!("DTSTAMP:{}", stamp))?;
+    write_line(writer, &format!("LAST-MODIFIED:{}", stamp))?;
+    write_line(writer, &format!("SEQUENCE:{}", revision.sequence))?;
+    if cancelled {
+        write_line(writer, "STATUS:CANCELLED")?;
+    }
+    write_line(writer, &format!("DTSTART:{}", date_time(date, start)))?;
+    write_line(writer, &format!("DTEND:{}", date_time(date, end)))?;
+    if term.weeks > 1 {
+        write_line(writer, &format!("RRULE:FREQ=WEEKLY;COUNT={}", term.weeks))?;
+    }
+    write_line(writer, &format!("SUMMARY:{}", escape(event.course_id())))?;
+    write_line(writer, &format!("LOCATION:{}", escape(room.id())))?;
+    let mut description = format!("Teacher: {}", event.teacher());
+    if !event.curriculum_ids().is_empty() {
+        description.push_str(&format!("\nCurricula: {}", event.curriculum_ids().join(", ")));
+    }
+    write_line(writer, &format!("DESCRIPTION:{}", escape(&description)))?;
+    write_line(writer, "END:VEVENT")
+}
+
+/// The UID of an event, made from its course and its place among the lectures of the course.
+fn uid(instance: &Instance, event_index: usize) -> String {
+    // NOTE: The UID can't depend on where the event is allocated, or the calendar would see a
+    // moved lecture as a new one. The event's place among the lectures of its course stays the
+    // same for as long as the instance does.
+    let course_id = instance.events()[event_index].course_id();
+    let lecture = instance.events()[..event_index]
+        .iter()
+        .filter(|other| other.course_id() == course_id)
+        .count();
+    format!("{}-{}@{}",
+            escape(course_id),
+            lecture,
+            escape(instance.name().unwrap_or("timetable")))
+}
+
+/// Write a content line, folding it so that no line is longer than 75 bytes.
+fn write_line<W: Write>(writer: &mut W, line: &str) -> std::io::Result<()> {
+    let mut rest = line;
+    let mut limit = 75;
+    while rest.len() > limit {
+        // Don't split a character in two.
+        let mut split = limit;
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+        write!(writer, "{}\r\n ", &rest[..split])?;
+        rest = &rest[split..];
+        // The space at the start of a continued line counts towards its length.
+        limit = 74;
+    }
+    write!(writer, "{}\r\n", rest)
+}
+
+/// Escape the characters that have a meaning in iCalendar text.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Replace the characters that can't be used in file names.
+fn file_name(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+fn date_time(date: Date, time: Time) -> String {
+    format!("{:04}{:02}{:02}T{:02}{:02}00",
+            date.year,
+            date.month,
+            date.day,
+            time.hour,
+            time.minute)
+}
+
+/// The number of seconds since 1970-01-01 UTC.
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// The time the specified number of seconds after 1970-01-01 UTC, as an iCalendar date-time.
+fn utc_date_time(seconds: i64) -> String {
+    let date = from_days(seconds.div_euclid(86400));
+    let seconds = seconds.rem_euclid(86400);
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+            date.year,
+            date.month,
+            date.day,
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60)
+}
+
+// NOTE: These convert between dates and days since 1970-01-01, using Howard Hinnant's
+// civil calendar algorithms.
+
+fn to_days(date: Date) -> i64 {
+    let year = if date.month <= 2 { date.year - 1 } else { date.year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let month = date.month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 +
+                      date.day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn from_days(days: i64) -> Date {
+    let days = days + 719468;
+    let era = (if days >= 0 { days } else { days - 146096 }) / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 -
+                       day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    Date::new(year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use candidate::CandidateSolution;
+    use data;
+    use render::View;
+    use super::*;
+
+    fn term(instance: &Instance) -> Term {
+        Term {
+            start: Date::new(2024, 2, 26),
+            periods: (0..instance.periods_per_day() as u32)
+                .map(|period| (Time::new(9 + period, 0), Time::new(9 + period, 50)))
+                .collect(),
+            weeks: 12,
+        }
+    }
+
+    /// The calendar of the view, unfolded into its content lines.
+    fn calendar(candidate: &CandidateSolution,
+                instance: &Instance,
+                revision: &Revision,
+                view: &View)
+                -> Vec<String> {
+        let mut bytes = Vec::new();
+        write(&mut bytes, candidate, instance, &term(instance), revision, view).unwrap();
+        String::from_utf8(bytes).unwrap().replace("\r\n ", "").lines().map(String::from).collect()
+    }
+
+    /// The lines of each VEVENT in the calendar.
+    fn events(lines: &[String]) -> Vec<Vec<String>> {
+        lines.split(|line| line == "BEGIN:VEVENT")
+            .skip(1)
+            .map(|lines| lines.iter().take_while(|line| *line != "END:VEVENT").cloned().collect())
+            .collect()
+    }
+
+    fn field<'a>(event: &'a [String], name: &str) -> Option<&'a str> {
+        event.iter().find(|line| line.starts_with(name)).map(|line| &line[name.len() + 1..])
+    }
+
+    #[test]
+    fn dates_convert_to_and_from_days() {
+        assert_eq!(to_days(Date::new(1970, 1, 1)), 0);
+        assert_eq!(to_days(Date::new(1969, 12, 31)), -1);
+        assert_eq!(to_days(Date::new(2000, 3, 1)), 11017);
+        assert_eq!(to_days(Date::new(2024, 2, 29)), 19782);
+        assert_eq!(to_days(Date::new(1600, 1, 1)), -135140);
+        for days in (-200000..200000).step_by(97) {
+            assert_eq!(to_days(from_days(days)), days);
+        }
+
+        assert_eq!(Date::new(2024, 2, 28).add_days(1), Date::new(2024, 2, 29));
+        assert_eq!(Date::new(2023, 2, 28).add_days(1), Date::new(2023, 3, 1));
+        assert_eq!(Date::new(2100, 2, 28).add_days(1), Date::new(2100, 3, 1));
+        assert_eq!(Date::new(2024, 12, 31).add_days(1), Date::new(2025, 1, 1));
+        assert_eq!(utc_date_time(951829509), "20000229T130509Z");
+    }
+
+    #[test]
+    fn long_lines_are_folded_at_75_octets() {
+        let line = format!("DESCRIPTION:{}", "Théorie des graphes, ".repeat(12));
+        let mut bytes = Vec::new();
+        write_line(&mut bytes, &line).unwrap();
+        let written = String::from_utf8(bytes).unwrap();
+
+        assert!(written.ends_with("\r\n"));
+        let lines: Vec<&str> = written[..written.len() - 2].split("\r\n").collect();
+        assert!(lines.len() > 1);
+        for (index, folded) in lines.iter().enumerate() {
+            assert!(folded.len() <= 75, "Line {:?} is longer than 75 octets.", folded);
+            assert_eq!(index > 0, folded.starts_with(' '));
+        }
+        assert_eq!(written.replace("\r\n ", ""), format!("{}\r\n", line));
+    }
+
+    #[test]
+    fn uids_stay_the_same_when_lectures_move() {
+        let instance = data::test_instance("ical_uids");
+        let mut candidate = CandidateSolution::new(&instance);
+        for event_index in 0..instance.num_events() {
+            candidate.allocate_event(event_index, 0, Some(event_index), &instance);
+        }
+        let mut altered = candidate.clone();
+        altered.swap((0, 0), (0, 1), &instance);
+
+        let first = Revision {
+            sequence: 1,
+            time: 0,
+            previous: None,
+        };
+        let second = Revision {
+            sequence: 2,
+            time: 60,
+            previous: Some(&candidate),
+        };
+        let teacher = View::Teacher(instance.events()[0].teacher().to_owned());
+        let before = events(&calendar(&candidate, &instance, &first, &teacher));
+        let after = events(&calendar(&altered, &instance, &second, &teacher));
+        assert!(!before.is_empty());
+        assert_eq!(before.len(), after.len());
+        for (event, other) in before.iter().zip(after.iter()) {
+            assert_eq!(field(event, "UID"), field(other, "UID"));
+            assert_eq!(field(event, "SEQUENCE"), Some("1"));
+            assert_eq!(field(other, "SEQUENCE"), Some("2"));
+        }
+        let uids: Vec<&str> = before.iter().filter_map(|event| field(event, "UID")).collect();
+        assert_eq!(uids[0], "C0-0@Test");
+        for (index, uid) in uids.iter().enumerate() {
+            assert!(!uids[..index].contains(uid));
+        }
+
+        // The lecture that left room R0 is cancelled in its calendar.
+        let room = events(&calendar(&altered, &instance, &second, &View::Room(0)));
+        let cancelled: Vec<&Vec<String>> = room.iter()
+            .filter(|event| field(event, "STATUS") == Some("CANCELLED"))
+            .collect();
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(field(cancelled[0], "UID"), field(&before[0], "UID"));
+        assert_eq!(field(cancelled[0], "LOCATION"), Some("R0"));
+    }
+}
@@ -1,10 +1,16 @@
 extern crate rand;
 extern crate toml;
+extern crate rustc_serialize;
 
 pub mod data;
+pub mod alteration;
 pub mod boxed_slice2d;
 pub mod candidate;
+pub mod evaluation;
+pub mod render;
+pub mod ical;
 pub mod allocation;
 pub mod genetic;
+pub mod perturbation;
 pub mod options;
 pub mod util;
@@ -1,10 +1,13 @@
 use toml::*;
 
 use allocation::*;
+use data::Formulation;
 use genetic::*;
 
 pub struct Options {
     pub strategy: Box<AllocationStrategy>,
+    /// The formulation to judge timetables by, if the options chose one.
+    pub formulation: Option<Formulation>,
 }
 
 /// Attempt to load an options file
@@ -25,7 +28,21 @@ pub fn load_options(path: &str) -> Result<Options, String> {
         _ => return Err("Unrecognized strategy specified.".to_owned()),
     };
 
-    Ok(Options { strategy: strategy })
+    let formulation = match table.lookup("timetable.formulation").map(|name| name.as_str()) {
+        Some(Some(name)) => {
+            match Formulation::from_name(name) {
+                Some(formulation) => Some(formulation),
+                None => return Err(format!("Unrecognized formulation {} specified.", name)),
+            }
+        }
+        Some(None) => return Err(format!("timetable.formulation must be a string in {}", path)),
+        None => None,
+    };
+
+    Ok(Options {
+        strategy,
+        formulation,
+    })
 }
 
 /// Load the Toml file, and return a table of all the entries in it.
@@ -0,0 +1,330 @@
+use candidate::*;
+use allocation::*;
+use data::instance::*;
+
+/// The result of reallocating displaced events into an existing solution.
+/// Keeps track of how many of the untouched events had to be moved, as a second objective to
+/// the number of violations.
+pub struct PerturbedSolution {
+    candidate: CandidateSolution,
+    moved_events: usize,
+    unplaced_events: Vec<usize>,
+}
+
+impl PerturbedSolution {
+    pub fn new(candidate: CandidateSolution,
+               moved_events: usize,
+               unplaced_events: Vec<usize>)
+               -> Self {
+        PerturbedSolution {
+            candidate,
+            moved_events,
+            unplaced_events,
+        }
+    }
+
+    pub fn candidate(&self) -> &CandidateSolution {
+        &self.candidate
+    }
+
+    pub fn into_candidate(self) -> CandidateSolution {
+        self.candidate
+    }
+
+    #[inline(always)]
+    pub fn cost(&self) -> Cost {
+        self.candidate.cost()
+    }
+
+    #[inline(always)]
+    pub fn violations(&self) -> usize {
+        self.candidate.violations()
+    }
+
+    /// The number of events (other than the displaced ones) that are no longer where they were
+    /// in the original solution.
+    #[inline(always)]
+    pub fn moved_events(&self) -> usize {
+        self.moved_events
+    }
+
+    /// The displaced events that no slot could be found for, which are left unallocated.
+    pub fn unplaced_events(&self) -> &[usize] {
+        &self.unplaced_events
+    }
+}
+
+/// A reallocation strategy that re-inserts displaced events while moving as few of the
+/// already allocated events as possible.
+///
+/// Each displaced event is either put straight into a free slot, or is given the slot of an
+/// allocated event, which is in turn moved into a free slot. Whichever option leaves the
+/// solution with the fewest hard violations wins. Ties go to the option that moves the fewest
+/// events, and then to the one with the lowest soft penalty, so that a published timetable is
+/// only disturbed further to make it feasible, never just to make it nicer. A free slot that adds
+/// no cost is taken as soon as it is found.
+pub struct MinimalPerturbationStrategy;
+
+impl MinimalPerturbationStrategy {
+    pub fn new() -> Self {
+        MinimalPerturbationStrategy
+    }
+}
+
+impl Default for MinimalPerturbationStrategy {
+    fn default() -> Self {
+        MinimalPerturbationStrategy::new()
+    }
+}
+
+/// A possible way to reinsert an event.
+#[derive(Clone, Copy)]
+enum Insertion {
+    /// Put the event into this free slot.
+    Direct((usize, usize)),
+    /// Put the event into the first slot, moving the event currently there into the second.
+    Eject((usize, usize), (usize, usize)),
+}
+
+impl ReallocationStrategy for MinimalPerturbationStrategy {
+    fn reallocate(&mut self,
+                  candidate: &CandidateSolution,
+                  events: &[usize],
+                  instance: &Instance)
+                  -> PerturbedSolution {
+        let original = candidate;
+        let mut candidate = candidate.clone();
+        // The instance may have been restricted since the candidate was last evaluated.
+        candidate.recompute_from_scratch(instance);
+
+        // Deal with the most constrained events first, while there is still room to move.
+        let mut events = events.to_owned();
+        events.sort_by_key(|&event_index| num_valid_slots(event_index, instance));
+
+        let mut unplaced_events = Vec::new();
+        let mut moved_events = 0;
+        for event_index in events {
+            let mut best: Option<(Insertion, CostDelta, usize)> = None;
+            for insertion in possible_insertions(event_index, &candidate, instance) {
+                let (delta, moved) =
+                    try_insertion(insertion, event_index, &candidate, original, instance);
+                let is_better = match best {
+                    Some((_, best_delta, best_moved)) => {
+                        (delta.hard, moved, delta.soft) <
+                        (best_delta.hard, best_moved, best_delta.soft)
+                    }
+                    None => true,
+                };
+                if is_better {
+                    best = Some((insertion, delta, moved));
+                }
+                // A slot that adds no cost without moving anything is good enough, so no sense
+                // in continuing.
+                if delta.hard < 0 && delta.soft <= 0 && moved == 0 {
+                    break;
+                }
+            }
+
+            match best {
+                Some((insertion, _, moved)) => {
+                    apply_insertion(insertion, event_index, &mut candidate, instance);
+                    moved_events += moved;
+                }
+                None => unplaced_events.push(event_index),
+            }
+        }
+
+        debug_assert_eq!(moved_events, count_moved_events(original, &candidate));
+        PerturbedSolution::new(candidate, moved_events, unplaced_events)
+    }
+}
+
+/// The number of timeslot/room combinations the event could be allocated to without breaking
+/// its own room and timeslot restrictions.
+fn num_valid_slots(event_index: usize, instance: &Instance) -> usize {
+    let event = instance.event(event_index).expect("Invalid event index provided.");
+    let num_rooms = match event.valid_rooms() {
+        Some(rooms) => rooms.len(),
+        None => instance.num_rooms(),
+    };
+    // NOTE: A timeslot can be banned more than once, so count the ones that aren't banned.
+    let num_timeslots = instance.timeslots()
+        .iter()
+        .filter(|timeslot| !event.banned_timeslots().contains(timeslot))
+        .count();
+    num_rooms * num_timeslots
+}
+
+/// Lists every insertion of the event that could be made into the candidate, without putting the
+/// event, or an event it ejects, into a timeslot or room that event is banned from. Direct
+/// insertions come first, as they do not move anything.
+fn possible_insertions(event_index: usize,
+                       candidate: &CandidateSolution,
+                       instance: &Instance)
+                       -> Vec<Insertion> {
+    let mut free = Vec::new();
+    let mut taken = Vec::new();
+    for timeslot_index in 0..candidate.num_timeslots() {
+        for room_index in 0..candidate.num_rooms() {
+            if candidate.get_allocation(timeslot_index, room_index).is_some() {
+                taken.push((timeslot_index, room_index));
+            } else {
+                free.push((timeslot_index, room_index));
+            }
+        }
+    }
+
+    let mut insertions: Vec<Insertion> = free.iter()
+        .filter(|&&slot| is_allowed(event_index, slot, instance))
+        .map(|&slot| Insertion::Direct(slot))
+        .collect();
+    for &slot in taken.iter().filter(|&&slot| is_allowed(event_index, slot, instance)) {
+        let ejected = candidate.get_allocation_with_index(slot)
+            .expect("A taken slot has no allocation.")
+            .event_index();
+        for &free_slot in free.iter() {
+            if is_allowed(ejected, free_slot, instance) {
+                insertions.push(Insertion::Eject(slot, free_slot));
+            }
+        }
+    }
+    insertions
+}
+
+/// Returns true if the event is not banned from the timeslot or the room of the slot.
+fn is_allowed(event_index: usize, slot: (usize, usize), instance: &Instance) -> bool {
+    let (timeslot_index, room_index) = slot;
+    let event = instance.event(event_index).expect("Invalid event index provided.");
+    let timeslot = instance.timeslot(timeslot_index).expect("Invalid timeslot index provided.");
+    !event.banned_timeslots().contains(timeslot) &&
+    event.valid_rooms().is_none_or(|rooms| rooms.contains(&room_index))
+}
+
+/// Performs the insertion on the candidate.
+fn apply_insertion(insertion: Insertion,
+                   event_index: usize,
+                   candidate: &mut CandidateSolution,
+                   instance: &Instance) {
+    match insertion {
+        Insertion::Direct((timeslot_index, room_index)) => {
+            candidate.allocate_event(timeslot_index, room_index, Some(event_index), instance);
+        }
+        Insertion::Eject((timeslot_index, room_index), (free_timeslot, free_room)) => {
+            let ejected = candidate.get_allocation(timeslot_index, room_index)
+                .map(|allocation| allocation.event_index());
+            candidate.allocate_event(free_timeslot, free_room, ejected, instance);
+            candidate.allocate_event(timeslot_index, room_index, Some(event_index), instance);
+        }
+    }
+}
+
+/// Returns the change in cost the insertion would make, and the number of events that were where
+/// they are in the original solution that it would move, without making it.
+fn try_insertion(insertion: Insertion,
+                 event_index: usize,
+                 candidate: &CandidateSolution,
+                 original: &CandidateSolution,
+                 instance: &Instance)
+                 -> (CostDelta, usize) {
+    match insertion {
+        Insertion::Direct((timeslot_index, room_index)) => {
+            let delta =
+                candidate.allocate_delta(timeslot_index, room_index, Some(event_index), instance);
+            (delta, 0)
+        }
+        Insertion::Eject(slot, free_slot) => {
+            let ejected = candidate.get_allocation_with_index(slot)
+                .map(|allocation| allocation.event_index());
+            let delta =
+                candidate.allocations_delta(&[(free_slot, ejected), (slot, Some(event_index))],
+                                            instance);
+            // A free slot was free in the original too, so the only event that can be moved
+            // is the ejected one, if it hadn't been moved already.
+            let was_in_place = original.get_allocation_with_index(slot)
+                .is_some_and(|allocation| Some(allocation.event_index()) == ejected);
+            (delta, was_in_place as usize)
+        }
+    }
+}
+
+/// Counts the events allocated in the original solution that are no longer in the same slot.
+pub fn count_moved_events(original: &CandidateSolution, altered: &CandidateSolution) -> usize {
+    let mut moved_events = 0;
+    for timeslot_index in 0..original.num_timeslots() {
+        for room_index in 0..original.num_rooms() {
+            if let Some(allocation) = original.get_allocation(timeslot_index, room_index) {
+                let still_here = altered.get_allocation(timeslot_index, room_index)
+                    .map(|other| other.event_index() == allocation.event_index())
+                    .unwrap_or(false);
+                if !still_here {
+                    moved_events += 1;
+                }
+            }
+        }
+    }
+    moved_events
+}
+
+#[cfg(test)]
+mod tests {
+    use data;
+    use super::*;
+
+    #[test]
+    fn insertions_keep_events_out_of_banned_slots() {
+        let mut instance = data::test_instance("insertions");
+        let num_rooms = instance.num_rooms();
+        let first_timeslot = *instance.timeslot(0).unwrap();
+        let third_timeslot = *instance.timeslot(2).unwrap();
+        {
+            let event = instance.event_mut(0).unwrap();
+            event.ban_timeslot(first_timeslot);
+            event.ban_room(0, num_rooms);
+        }
+        instance.event_mut(1).unwrap().ban_timeslot(third_timeslot);
+
+        let mut candidate = CandidateSolution::new(&instance);
+        candidate.allocate_event(3, 1, Some(1), &instance);
+        candidate.allocate_event(0, 2, Some(2), &instance);
+
+        let insertions = possible_insertions(0, &candidate, &instance);
+        let mut ejects_event_1 = false;
+        for insertion in insertions {
+            let (slot, ejected_to) = match insertion {
+                Insertion::Direct(slot) => (slot, None),
+                Insertion::Eject(slot, free_slot) => (slot, Some(free_slot)),
+            };
+            // Event 0 can't go into the first timeslot, or the first room.
+            assert!(slot.0 != 0 && slot.1 != 0);
+            if let Some(free_slot) = ejected_to {
+                // Event 2 is in the first timeslot, so is never ejected, and event 1 can't be
+                // moved into the third timeslot.
+                assert_eq!(slot, (3, 1));
+                assert!(free_slot.0 != 2);
+                ejects_event_1 = true;
+            }
+        }
+        assert!(ejects_event_1);
+    }
+
+    #[test]
+    fn valid_slots_count_a_timeslot_banned_twice_once() {
+        let mut instance = data::test_instance("valid_slots");
+        let num_rooms = instance.num_rooms();
+        let timeslots = instance.timeslots().to_owned();
+        // C1 has no unavailability constraints of its own.
+        let event_index = instance.events()
+            .iter()
+            .position(|event| event.course_id() == "C1")
+            .unwrap();
+        {
+            // Ban every timeslot but the first, and the second twice.
+            let event = instance.event_mut(event_index).unwrap();
+            event.ban_timeslot(timeslots[1]);
+            for &timeslot in timeslots[1..].iter() {
+                event.add_banned_timeslot(timeslot);
+            }
+        }
+        assert_eq!(num_valid_slots(event_index, &instance), num_rooms);
+    }
+}
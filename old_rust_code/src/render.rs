@@ -0,0 +1,311 @@
+use std::fmt::Write;
+
+use candidate::CandidateSolution;
+use data::*;
+
+/// Whose timetable to show.
+#[derive(Clone, PartialEq, Debug)]
+pub enum View {
+    /// The lectures held in a room, by room index.
+    Room(usize),
+    /// The lectures taught by a teacher, by teacher id.
+    Teacher(String),
+    /// The lectures of every course in a curriculum, by curriculum index.
+    Curriculum(usize),
+}
+
+impl View {
+    /// Every room, teacher and curriculum in the instance, in that order.
+    pub fn all(instance: &Instance) -> Vec<View> {
+        let mut views: Vec<View> = (0..instance.num_rooms()).map(View::Room).collect();
+        let mut teachers: Vec<&str> = Vec::new();
+        for event in instance.events() {
+            if !teachers.contains(&event.teacher()) {
+                teachers.push(event.teacher());
+            }
+        }
+        views.extend(teachers.into_iter().map(|teacher| View::Teacher(teacher.to_owned())));
+        views.extend((0..instance.curricula().len()).map(View::Curriculum));
+        views
+    }
+
+    /// A heading for the timetable, such as "Room B".
+    pub fn title(&self, instance: &Instance) -> String {
+        match *self {
+            View::Room(room_index) => {
+                format!("Room {}",
+                        instance.room(room_index).expect("Invalid room index in a View.").id())
+            }
+            View::Teacher(ref teacher) => format!("Teacher {}", teacher),
+            View::Curriculum(curriculum_index) => {
+                format!("Curriculum {}",
+                        instance.curriculum(curriculum_index)
+                            .expect("Invalid curriculum index in a View.")
+                            .id())
+            }
+        }
+    }
+
+    /// Returns true if the event, allocated to the room, belongs in this timetable.
+    pub fn shows(&self, event: &Event, room_index: usize, instance: &Instance) -> bool {
+        match *self {
+            View::Room(other) => other == room_index,
+            View::Teacher(ref teacher) => event.teacher() == teacher,
+            View::Curriculum(curriculum_index) => {
+                instance.curriculum(curriculum_index)
+                    .is_some_and(|curriculum| curriculum.contains_course(event.course_id()))
+            }
+        }
+    }
+}
+
+/// A lecture shown in a cell of the grid.
+struct Entry {
+    text: String,
+    /// Whether the allocation has any violations.
+    violated: bool,
+}
+
+/// A day × period grid of the lectures in one view. There can be more than one lecture in a cell
+/// when a teacher or curriculum clashes with itself.
+struct Grid {
+    title: String,
+    /// The entries of each cell, indexed by [period][day].
+    cells: Vec<Vec<Vec<Entry>>>,
+}
+
+impl Grid {
+    fn new(candidate: &CandidateSolution, instance: &Instance, view: &View) -> Self {
+        let mut cells: Vec<Vec<Vec<Entry>>> = (0..instance.periods_per_day())
+            .map(|_| (0..instance.days()).map(|_| Vec::new()).collect())
+            .collect();
+
+        for allocation in candidate.allocations() {
+            let event = instance.event(allocation.event_index())
+                .expect("Invalid event index found in an allocation.");
+            let room_index = allocation.room_index();
+            if !view.shows(event, room_index, instance) {
+                continue;
+            }
+            let room = instance.room(room_index)
+                .expect("Invalid room index found in an allocation.");
+            let timeslot = instance.timeslot(allocation.timeslot_index())
+                .expect("Invalid timeslot index found in an allocation.");
+
+            // The room is already known when looking at a room's timetable.
+            let text = match *view {
+                View::Room(_) => event.course_id().to_owned(),
+                _ => format!("{} ({})", event.course_id(), room.id()),
+            };
+            cells[timeslot.period][timeslot.day].push(Entry {
+                text,
+                violated: allocation.violations() != 0,
+            });
+        }
+
+        Grid {
+            title: view.title(instance),
+            cells,
+        }
+    }
+}
+
+/// Render the view of the candidate as a plain text grid, with a column for each day and a row
+/// for each period. Lectures whose allocation has violations are marked with a '*'.
+pub fn text(candidate: &CandidateSolution, instance: &Instance, view: &View) -> String {
+    let grid = Grid::new(candidate, instance, view);
+
+    // The text of every cell, with the headings in the first row and column.
+    let mut rows = Vec::with_capacity(instance.periods_per_day() + 1);
+    let mut headings = vec![String::new()];
+    headings.extend((0..instance.days()).map(|day| format!("Day {}", day)));
+    rows.push(headings);
+    for (period, cells) in grid.cells.iter().enumerate() {
+        let mut row = vec![format!("Period {}", period)];
+        for entries in cells.iter() {
+            let texts: Vec<String> = entries.iter()
+                .map(|entry| if entry.violated {
+                    format!("{}*", entry.text)
+                } else {
+                    entry.text.clone()
+                })
+                .collect();
+            row.push(texts.join(", "));
+        }
+        rows.push(row);
+    }
+
+    let num_columns = instance.days() + 1;
+    let widths: Vec<usize> = (0..num_columns)
+        .map(|column| rows.iter().map(|row| row[column].chars().count()).max().unwrap_or(0))
+        .collect();
+
+    let mut output = String::new();
+    writeln!(output, "{}", grid.title).unwrap();
+    for (row_index, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = row.iter()
+            .zip(widths.iter())
+            .map(|(cell, &width)| format!("{:1$}", cell, width))
+            .collect();
+        writeln!(output, "| {} |", cells.join(" | ")).unwrap();
+        if row_index == 0 {
+            let rules: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+            writeln!(output, "|-{}-|", rules.join("-|-")).unwrap();
+        }
+    }
+    let any_violated = grid.cells
+        .iter()
+        .any(|cells| cells.iter().any(|entries| entries.iter().any(|entry| entry.violated)));
+    if any_violated {
+        writeln!(output, "* has violations").unwrap();
+    }
+    output
+}
+
+/// Render the views of the candidate as a standalone HTML page, with a table for each view.
+/// Cells holding a lecture whose allocation has violations are highlighted.
+pub fn html(candidate: &CandidateSolution, instance: &Instance, views: &[View]) -> String {
+    let title = format!("Timetable for {}", instance.name().unwrap_or("an unnamed instance"));
+
+    let mut output = String::new();
+    writeln!(output, "<!DOCTYPE html>").unwrap();
+    writeln!(output, "<html>").unwrap();
+    writeln!(output, "<head>").unwrap();
+    writeln!(output, "<meta charset=\"utf-8\">").unwrap();
+    writeln!(output, "<title>{}</title>", escape(&title)).unwrap();
+    writeln!(output, "<style>").unwrap();
+    writeln!(output, "table {{ border-collapse: collapse; margin-bottom: 2em; }}").unwrap();
+    writeln!(output, "th, td {{ border: 1px solid #999; padding: 0.25em 0.5em; }}").unwrap();
+    writeln!(output, "td.violated {{ background-color: #f4b6b6; }}").unwrap();
+    writeln!(output, "</style>").unwrap();
+    writeln!(output, "</head>").unwrap();
+    writeln!(output, "<body>").unwrap();
+    writeln!(output, "<h1>{}</h1>", escape(&title)).unwrap();
+
+    for view in views.iter() {
+        let grid = Grid::new(candidate, instance, view);
+        writeln!(output, "<h2>{}</h2>", escape(&grid.title)).unwrap();
+        writeln!(output, "<table>").unwrap();
+        write!(output, "<tr><th></th>").unwrap();
+        for day in 0..instance.days() {
+            write!(output, "<th>Day {}</th>", day).unwrap();
+        }
+        writeln!(output, "</tr>").unwrap();
+
+        for (period, cells) in grid.cells.iter().enumerate() {
+            write!(output, "<tr><th>Period {}</th>", period).unwrap();
+            for entries in cells.iter() {
+                if entries.iter().any(|entry| entry.violated) {
+                    write!(output, "<td class=\"violated\">").unwrap();
+                } else {
+                    write!(output, "<td>").unwrap();
+                }
+                let texts: Vec<String> = entries.iter().map(|entry| escape(&entry.text)).collect();
+                write!(output, "{}</td>", texts.join("<br>")).unwrap();
+            }
+            writeln!(output, "</tr>").unwrap();
+        }
+        writeln!(output, "</table>").unwrap();
+    }
+
+    writeln!(output, "</body>").unwrap();
+    writeln!(output, "</html>").unwrap();
+    output
+}
+
+/// Escape the characters that have a meaning in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std;
+    use std::io::Write;
+
+    use candidate::CandidateSolution;
+    use data;
+    use super::*;
+
+    /// The test instance, with C0 (T0) on day 0 period 1 in R0, C1 (T1) on day 1 period 0 in R0,
+    /// C2 (T0) on day 1 period 0 in R1, and C3 (T2) on day 2 period 2 in R2. C1 and C2 clash, as
+    /// they are both in curriculum Q0.
+    fn candidate(instance: &Instance) -> CandidateSolution {
+        let mut candidate = CandidateSolution::new(instance);
+        for &(course_id, day, period, room_index) in
+            [("C0", 0, 1, 0), ("C1", 1, 0, 0), ("C2", 1, 0, 1), ("C3", 2, 2, 2)].iter() {
+            let event_index = instance.events()
+                .iter()
+                .position(|event| event.course_id() == course_id)
+                .unwrap();
+            let timeslot_index = instance.to_timeslot_index(day, period);
+            candidate.allocate_event(timeslot_index, room_index, Some(event_index), instance);
+        }
+        candidate
+    }
+
+    /// The text in the cell of a text grid.
+    fn cell(text: &str, day: usize, period: usize) -> String {
+        // The title, the headings and the rule come before the first period.
+        let row = text.lines().nth(3 + period).unwrap();
+        row.split('|').nth(day + 2).unwrap().trim().to_owned()
+    }
+
+    #[test]
+    fn room_view_shows_the_courses_in_the_room() {
+        let instance = data::test_instance("render_room");
+        let text = text(&candidate(&instance), &instance, &View::Room(0));
+        assert_eq!(text.lines().next(), Some("Room R0"));
+        assert_eq!(cell(&text, 0, 1), "C0");
+        assert_eq!(cell(&text, 1, 0), "C1*");
+        assert!(!text.contains("C2") && !text.contains("C3"));
+        assert_eq!(text.lines().last(), Some("* has violations"));
+    }
+
+    #[test]
+    fn teacher_view_shows_the_courses_taught_and_their_rooms() {
+        let instance = data::test_instance("render_teacher");
+        let text = text(&candidate(&instance), &instance, &View::Teacher("T0".to_owned()));
+        assert_eq!(text.lines().next(), Some("Teacher T0"));
+        assert_eq!(cell(&text, 0, 1), "C0 (R0)");
+        assert_eq!(cell(&text, 1, 0), "C2 (R1)*");
+        assert!(!text.contains("C1") && !text.contains("C3"));
+    }
+
+    #[test]
+    fn curriculum_view_shows_the_courses_of_the_curriculum() {
+        let instance = data::test_instance("render_curriculum");
+        // Q1 is C2, C3 and C4.
+        let text = text(&candidate(&instance), &instance, &View::Curriculum(1));
+        assert_eq!(text.lines().next(), Some("Curriculum Q1"));
+        assert_eq!(cell(&text, 1, 0), "C2 (R1)*");
+        assert_eq!(cell(&text, 2, 2), "C3 (R2)");
+        assert!(!text.contains("C0") && !text.contains("C1"));
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        let contents = data::TEST_INSTANCE.replace("Name: Test", "Name: A&B <Test>")
+            .replace("C0 ", "<b>C&\"0\"</b> ");
+        let path = std::env::temp_dir()
+            .join(format!("time_table_{}_render_html.ectt", std::process::id()));
+        let path = path.to_str().expect("Temporary path is not valid unicode.");
+        std::fs::File::create(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .expect("Could not write the test file.");
+        let instance = data::load(path);
+        let _ = std::fs::remove_file(path);
+        let instance = instance.unwrap();
+
+        let mut candidate = CandidateSolution::new(&instance);
+        candidate.allocate_event(1, 0, Some(0), &instance);
+        let html = html(&candidate, &instance, &View::all(&instance));
+        assert!(html.contains("<title>Timetable for A&amp;B &lt;Test&gt;</title>"));
+        assert!(html.contains("<td>&lt;b&gt;C&amp;&quot;0&quot;&lt;/b&gt;</td>"));
+        assert!(html.contains("&lt;b&gt;C&amp;&quot;0&quot;&lt;/b&gt; (R0)"));
+        assert!(!html.contains("<b>") && !html.contains("A&B"));
+    }
+}